@@ -0,0 +1,171 @@
+use ethers::abi::{ParamType, Token};
+use ethers::types::{I256, U256};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::address::parse_address;
+
+/// Why a JSON value could not be read as a Solidity value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError {
+    pub path: String, // The offending value, e.g. `recipients[2]` or `grants[0].amount`
+    pub reason: String,
+}
+
+impl std::fmt::Display for ValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.reason)
+    }
+}
+
+/// Names of an ABI parameter and its tuple components. ethabi's `ParamType` drops
+/// component names, so they are read from the raw ABI JSON alongside it.
+#[derive(Deserialize, Default)]
+pub(crate) struct AbiParamNames {
+    #[serde(default)]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) components: Vec<AbiParamNames>,
+}
+
+/// Converts a JSON value into an ABI token of type `kind`, reporting failures
+/// against `path`.
+pub(crate) fn json_to_token(
+    value: &Value,
+    kind: &ParamType,
+    components: &[AbiParamNames],
+    path: &str,
+) -> Result<Token, ValueError> {
+    let fail = |reason: String| ValueError { path: path.to_string(), reason };
+    match kind {
+        ParamType::Address => {
+            let s = value.as_str().ok_or_else(|| fail(format!("expected an address string, got {}", value)))?;
+            parse_address(s, true)
+                .map(Token::Address)
+                .map_err(|e| fail(format!("'{}' is not a valid address: {}", s, e)))
+        }
+        ParamType::Bool => value.as_bool()
+            .map(Token::Bool)
+            .ok_or_else(|| fail(format!("expected true or false, got {}", value))),
+        ParamType::String => value.as_str()
+            .map(|s| Token::String(s.to_string()))
+            .ok_or_else(|| fail(format!("expected a string, got {}", value))),
+        ParamType::Uint(bits) => {
+            let n = parse_uint(value).map_err(&fail)?;
+            if n.bits() > *bits {
+                return Err(fail(format!("{} does not fit in uint{}", n, bits)));
+            }
+            Ok(Token::Uint(n))
+        }
+        ParamType::Int(bits) => {
+            let n = parse_int(value).map_err(&fail)?;
+            if *bits < 256 {
+                let bound = I256::one() << (bits - 1);
+                if n >= bound || n < -bound {
+                    return Err(fail(format!("{} does not fit in int{}", n, bits)));
+                }
+            }
+            Ok(Token::Int(n.into_raw()))
+        }
+        ParamType::Bytes => parse_hex_bytes(value).map(Token::Bytes).map_err(&fail),
+        ParamType::FixedBytes(size) => {
+            let bytes = parse_hex_bytes(value).map_err(&fail)?;
+            if bytes.len() != *size {
+                return Err(fail(format!("expected {} bytes, got {}", size, bytes.len())));
+            }
+            Ok(Token::FixedBytes(bytes))
+        }
+        ParamType::Array(inner) => {
+            let items = value.as_array().ok_or_else(|| fail(format!("expected an array, got {}", value)))?;
+            items.iter().enumerate()
+                .map(|(i, item)| json_to_token(item, inner, components, &format!("{}[{}]", path, i)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Token::Array)
+        }
+        ParamType::FixedArray(inner, len) => {
+            let items = value.as_array().ok_or_else(|| fail(format!("expected an array, got {}", value)))?;
+            if items.len() != *len {
+                return Err(fail(format!("expected {} elements, got {}", len, items.len())));
+            }
+            items.iter().enumerate()
+                .map(|(i, item)| json_to_token(item, inner, components, &format!("{}[{}]", path, i)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Token::FixedArray)
+        }
+        ParamType::Tuple(kinds) => {
+            let mut tokens = Vec::with_capacity(kinds.len());
+            for (i, field_kind) in kinds.iter().enumerate() {
+                let field = components.get(i);
+                let field_name = field.map(|c| c.name.as_str()).filter(|n| !n.is_empty());
+                let field_value = match (value, field_name) {
+                    (Value::Array(items), _) => items.get(i),
+                    (Value::Object(map), Some(name)) => map.get(name),
+                    _ => return Err(fail(format!("expected a tuple as an array or object, got {}", value))),
+                };
+                let field_path = format!("{}.{}", path, field_name.map(str::to_string).unwrap_or_else(|| i.to_string()));
+                let field_value = field_value.ok_or_else(|| ValueError {
+                    path: field_path.clone(),
+                    reason: format!("missing tuple field of type {}", field_kind),
+                })?;
+                let nested = field.map(|c| c.components.as_slice()).unwrap_or_default();
+                tokens.push(json_to_token(field_value, field_kind, nested, &field_path)?);
+            }
+            Ok(Token::Tuple(tokens))
+        }
+    }
+}
+
+/// Accepts a non-negative JSON integer or a decimal / `0x`-hex string.
+pub(crate) fn parse_uint(value: &Value) -> Result<U256, String> {
+    match value {
+        Value::Number(n) => n.as_u64()
+            .map(U256::from)
+            .ok_or_else(|| format!("{} is not a non-negative integer", n)),
+        Value::String(s) if s.starts_with("0x") => U256::from_str_radix(&s[2..], 16)
+            .map_err(|_| format!("'{}' is not a valid hex integer", s)),
+        Value::String(s) => U256::from_dec_str(s)
+            .map_err(|_| format!("'{}' is not a valid unsigned integer", s)),
+        other => Err(format!("expected an integer, got {}", other)),
+    }
+}
+
+/// Accepts a JSON integer or a decimal string, optionally negative.
+pub(crate) fn parse_int(value: &Value) -> Result<I256, String> {
+    match value {
+        Value::Number(n) => n.as_i64()
+            .map(I256::from)
+            .ok_or_else(|| format!("{} is not an integer", n)),
+        Value::String(s) => I256::from_dec_str(s)
+            .map_err(|_| format!("'{}' is not a valid signed integer", s)),
+        other => Err(format!("expected an integer, got {}", other)),
+    }
+}
+
+fn parse_hex_bytes(value: &Value) -> Result<Vec<u8>, String> {
+    let s = value.as_str().ok_or_else(|| format!("expected a 0x-prefixed hex string, got {}", value))?;
+    let digits = s.strip_prefix("0x").ok_or_else(|| format!("'{}' is missing the 0x prefix", s))?;
+    hex::decode(digits).map_err(|e| format!("'{}' is not valid hex: {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(parse_uint(&json!("0xff")), Ok(U256::from(255)));
+        assert_eq!(parse_uint(&json!("1000000000000000000000")), Ok(U256::exp10(21)));
+        assert!(parse_uint(&json!(-1)).is_err());
+        assert_eq!(parse_int(&json!("-128")), Ok(I256::from(-128)));
+        assert!(parse_int(&json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_errors_name_the_offending_value() {
+        let kind = ParamType::Array(Box::new(ParamType::Uint(8)));
+        let err = json_to_token(&json!([1, 256]), &kind, &[], "amounts").unwrap_err();
+        assert_eq!(err.path, "amounts[1]");
+        assert_eq!(err.reason, "256 does not fit in uint8");
+    }
+}
@@ -0,0 +1,116 @@
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+
+/// Why a string was rejected as an Ethereum address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressError {
+    MissingPrefix,
+    WrongLength(usize), // Number of hex digits found after the prefix
+    NonHexCharacter { position: usize, character: char }, // `position` counts from the start of the string
+    BadChecksum { expected: String },
+    ZeroAddress,
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::MissingPrefix => write!(f, "missing '0x' prefix"),
+            AddressError::WrongLength(len) => write!(f, "expected 40 hex digits after '0x', found {}", len),
+            AddressError::NonHexCharacter { position, character } => {
+                write!(f, "non-hex character '{}' at position {}", character, position)
+            }
+            AddressError::BadChecksum { expected } => {
+                write!(f, "EIP-55 checksum mismatch, expected {}", expected)
+            }
+            AddressError::ZeroAddress => write!(f, "the zero address is not allowed here"),
+        }
+    }
+}
+
+/// Parses a `0x`-prefixed address and verifies its EIP-55 checksum.
+///
+/// All-lowercase and all-uppercase addresses carry no checksum and are accepted as-is;
+/// mixed-case addresses must match their checksummed form exactly. With `allow_zero`
+/// false, `0x000...0` is rejected as well.
+pub fn parse_address(input: &str, allow_zero: bool) -> Result<Address, AddressError> {
+    let digits = input.strip_prefix("0x").ok_or(AddressError::MissingPrefix)?;
+    if let Some((i, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(AddressError::NonHexCharacter { position: i + 2, character: c });
+    }
+    if digits.len() != 40 {
+        return Err(AddressError::WrongLength(digits.len()));
+    }
+
+    let address: Address = input.parse().map_err(|_| AddressError::WrongLength(digits.len()))?;
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        let expected = checksum(&address);
+        if expected != input {
+            return Err(AddressError::BadChecksum { expected });
+        }
+    }
+
+    if !allow_zero && address.is_zero() {
+        return Err(AddressError::ZeroAddress);
+    }
+    Ok(address)
+}
+
+/// EIP-55 checksummed form of `address`, used wherever addresses are displayed or stored.
+pub fn checksum(address: &Address) -> String {
+    to_checksum(address, None)
+}
+
+/// Serializes an address in its checksummed form, for `#[serde(serialize_with)]`.
+pub fn serialize_checksummed<S: serde::Serializer>(address: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&checksum(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from EIP-55.
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_valid_addresses() {
+        assert!(parse_address(CHECKSUMMED, false).is_ok());
+        assert!(parse_address("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359", false).is_ok());
+        assert!(parse_address(&CHECKSUMMED.to_lowercase(), false).is_ok());
+        assert!(parse_address(&format!("0x{}", CHECKSUMMED[2..].to_uppercase()), false).is_ok());
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let wrong = "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(
+            parse_address(wrong, false),
+            Err(AddressError::BadChecksum { expected: CHECKSUMMED.to_string() })
+        );
+    }
+
+    #[test]
+    fn test_malformed_addresses() {
+        assert_eq!(parse_address("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", false), Err(AddressError::MissingPrefix));
+        assert_eq!(parse_address("0x1234", false), Err(AddressError::WrongLength(4)));
+        assert_eq!(
+            parse_address("0xZZZZ567890123456789012345678901234567890", false),
+            Err(AddressError::NonHexCharacter { position: 2, character: 'Z' })
+        );
+    }
+
+    #[test]
+    fn test_zero_address() {
+        let zero = "0x0000000000000000000000000000000000000000";
+        assert_eq!(parse_address(zero, false), Err(AddressError::ZeroAddress));
+        assert_eq!(parse_address(zero, true), Ok(Address::zero()));
+    }
+
+    #[test]
+    fn test_checksum_round_trip() {
+        let address = parse_address(&CHECKSUMMED.to_lowercase(), false).unwrap();
+        assert_eq!(checksum(&address), CHECKSUMMED);
+    }
+}
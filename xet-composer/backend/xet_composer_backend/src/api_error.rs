@@ -0,0 +1,232 @@
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::deploy_engine::DeployError;
+use crate::sol_template_engine::TemplateError;
+
+/// Error returned by every API handler. Converts into a JSON body of the form
+/// `{"code": "...", "message": "...", "details": ...}` with a matching status code.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String), // Malformed request body
+    Kyc(String),
+    RateLimited(String), // Too many requests of one kind outstanding
+    Template(TemplateError),
+    Deploy(DeployError),
+    JobNotFound(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    details: Value,
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Kyc(_) => StatusCode::BAD_REQUEST,
+            ApiError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Template(e) => match e {
+                TemplateError::TemplateNotFound(_) => StatusCode::NOT_FOUND,
+                TemplateError::ValidationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                TemplateError::IoError(_) | TemplateError::TeraError(_) | TemplateError::InvalidSchema(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            },
+            ApiError::Deploy(e) => match e {
+                DeployError::CompilationFailed(_)
+                | DeployError::ConstructorArgError { .. }
+                | DeployError::InvalidPragma(_)
+                | DeployError::NoMatchingCompiler(_)
+                | DeployError::UnresolvedImports(_)
+                | DeployError::LinkError(_)
+                | DeployError::CostLimitExceeded(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                DeployError::Create2Error(_) => StatusCode::CONFLICT,
+                DeployError::EthersError(_) | DeployError::NoReceipt(_) => StatusCode::BAD_GATEWAY,
+                DeployError::IoError(_)
+                | DeployError::SolcError(_)
+                | DeployError::SignerError(_)
+                | DeployError::InvalidBytecode(_)
+                | DeployError::JsonError(_)
+                | DeployError::NoAbiFound(_)
+                | DeployError::NoBytecodeFound(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            ApiError::JobNotFound(_) => StatusCode::NOT_FOUND,
+        }
+    }
+
+    /// Machine-readable error code, stable across releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Kyc(_) => "kyc_failed",
+            ApiError::RateLimited(_) => "rate_limited",
+            ApiError::Template(e) => match e {
+                TemplateError::TemplateNotFound(_) => "template_not_found",
+                TemplateError::ValidationFailed(_) => "invalid_params",
+                TemplateError::TeraError(_) => "template_render_failed",
+                TemplateError::InvalidSchema(_) => "invalid_template_schema",
+                TemplateError::IoError(_) => "template_io_error",
+            },
+            ApiError::Deploy(e) => match e {
+                DeployError::CompilationFailed(_) => "compilation_failed",
+                DeployError::SolcError(_) => "compiler_error",
+                DeployError::InvalidPragma(_) => "invalid_pragma",
+                DeployError::NoMatchingCompiler(_) => "no_matching_compiler",
+                DeployError::UnresolvedImports(_) => "unresolved_imports",
+                DeployError::LinkError(_) => "link_failed",
+                DeployError::Create2Error(_) => "create2_failed",
+                DeployError::CostLimitExceeded(_) => "cost_limit_exceeded",
+                DeployError::ConstructorArgError { .. } => "invalid_constructor_args",
+                DeployError::EthersError(_) => "rpc_error",
+                DeployError::NoReceipt(_) => "deployment_not_confirmed",
+                DeployError::SignerError(_) => "signer_unavailable",
+                DeployError::InvalidBytecode(_) | DeployError::NoAbiFound(_) | DeployError::NoBytecodeFound(_) => {
+                    "invalid_artifact"
+                }
+                DeployError::IoError(_) | DeployError::JsonError(_) => "internal_error",
+            },
+            ApiError::JobNotFound(_) => "job_not_found",
+        }
+    }
+
+    /// Structured context for the error, `null` when there is none.
+    pub fn details(&self) -> Value {
+        match self {
+            ApiError::Template(TemplateError::ValidationFailed(issues)) => json!({ "violations": issues }),
+            ApiError::Template(TemplateError::TeraError(e)) => {
+                // Tera's top-level message rarely names the cause; include the whole chain.
+                let mut causes = Vec::new();
+                let mut source = std::error::Error::source(e);
+                while let Some(cause) = source {
+                    causes.push(cause.to_string());
+                    source = cause.source();
+                }
+                json!({ "causes": causes })
+            }
+            ApiError::Deploy(DeployError::SolcError(output)) => json!({ "solc_output": output }),
+            ApiError::Deploy(DeployError::CompilationFailed(diagnostics)) => json!({ "diagnostics": diagnostics }),
+            ApiError::Deploy(DeployError::UnresolvedImports(imports)) => json!({ "imports": imports }),
+            ApiError::Deploy(DeployError::ConstructorArgError { param, reason }) => {
+                json!({ "field": param, "reason": reason })
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
+            ApiError::Kyc(msg) => write!(f, "KYC validation failed: {}", msg),
+            ApiError::RateLimited(msg) => write!(f, "Rate limited: {}", msg),
+            ApiError::Template(e) => write!(f, "{}", e),
+            ApiError::Deploy(e) => write!(f, "{}", e),
+            ApiError::JobNotFound(id) => write!(f, "No deployment job with id {}", id),
+        }
+    }
+}
+
+impl From<TemplateError> for ApiError {
+    fn from(err: TemplateError) -> ApiError {
+        ApiError::Template(err)
+    }
+}
+
+impl From<DeployError> for ApiError {
+    fn from(err: DeployError) -> ApiError {
+        ApiError::Deploy(err)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> ApiError {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            eprintln!("Request failed with {}: {}", status, self);
+        } else {
+            println!("Request rejected with {}: {}", status, self);
+        }
+        let body = ErrorBody { code: self.code(), message: self.to_string(), details: self.details() };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solc_standard_json::{Diagnostic, Severity};
+    use crate::template_schema::ValidationIssue;
+
+    async fn response_json(error: ApiError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_validation_failure_is_422_with_violations() {
+        let error = ApiError::from(TemplateError::ValidationFailed(vec![ValidationIssue {
+            field: "duration".to_string(),
+            message: "must be >= 1".to_string(),
+        }]));
+        let (status, body) = response_json(error).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "invalid_params");
+        assert_eq!(body["details"]["violations"][0]["field"], "duration");
+    }
+
+    #[tokio::test]
+    async fn test_solc_failure_includes_output() {
+        let (status, body) = response_json(DeployError::SolcError("solc: command not found".to_string()).into()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "compiler_error");
+        assert_eq!(body["details"]["solc_output"], "solc: command not found");
+    }
+
+    #[tokio::test]
+    async fn test_compilation_failure_includes_diagnostics() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            error_code: Some("2314".to_string()),
+            kind: "ParserError".to_string(),
+            message: "Expected ';' but got '}'".to_string(),
+            formatted_message: None,
+            location: None,
+        };
+        let (status, body) = response_json(DeployError::CompilationFailed(vec![diagnostic]).into()).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "compilation_failed");
+        assert_eq!(body["details"]["diagnostics"][0]["error_code"], "2314");
+    }
+
+    #[tokio::test]
+    async fn test_status_codes() {
+        assert_eq!(ApiError::Kyc("bad".to_string()).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(ApiError::RateLimited("slow down".to_string()).status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(ApiError::from(TemplateError::TemplateNotFound("X".to_string())).status(), StatusCode::NOT_FOUND);
+        assert_eq!(ApiError::from(DeployError::EthersError("timeout".to_string())).status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(ApiError::from(DeployError::SignerError("none".to_string())).status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(ApiError::JobNotFound("abc".to_string()).status(), StatusCode::NOT_FOUND);
+
+        let (_, body) = response_json(DeployError::ConstructorArgError {
+            param: "_beneficiary".to_string(),
+            reason: "'0x12' is not a valid address".to_string(),
+        }.into()).await;
+        assert_eq!(body["details"]["field"], "_beneficiary");
+    }
+}
@@ -1,56 +1,92 @@
-use serde::Serialize;
+use ethers::abi::Abi;
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use chrono::Utc;
-use crate::deploy_engine::CompiledArtifact; // Assuming CompiledArtifact is here
+use crate::address::{checksum, parse_address};
+use crate::deploy_engine::{CompiledArtifact, Create2Info, DeploymentResult};
+use crate::solc_standard_json::{BytecodeRange, CompilerSettings, LinkReferences};
 
 #[derive(Serialize)]
 pub struct StorableArtifactData {
     pub contract_name: String,
-    pub abi: String,
-    pub bytecode: String,
+    pub source_name: String,
+    pub chain: String,
+    pub abi: Abi,
+    pub bytecode: String, // As compiled, i.e. before linking
+    pub link_references: LinkReferences,
+    pub libraries: BTreeMap<String, String>, // Fully qualified library name -> address it was linked to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create2: Option<Create2Info>, // Factory, salt and init code hash of a CREATE2 deployment
+    pub deployed_bytecode: String,
+    pub metadata: String,
+    pub storage_layout: Value,
+    pub method_identifiers: BTreeMap<String, String>,
+    pub immutable_references: BTreeMap<String, Vec<BytecodeRange>>,
+    pub source_map: String,
+    pub deployed_source_map: String,
+    pub compiler_version: Option<String>,
     pub address: String,
+    pub deployer: String,
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub gas_used: u64,
     pub deployed_at: i64,
+    pub compiler_settings: CompilerSettings,
 }
 
 /// Stores the compiled artifact and deployment information to a JSON file.
 ///
-/// The file will be saved in a `deployments` directory, which is expected to be
-/// at `../../deployments/` relative to the backend executable's runtime location.
-/// This typically means `xet-composer/deployments/` in the project root.
+/// The file will be saved in `deployments_dir`, the configured deployments directory
+/// (`xet-composer/deployments/` by default). Returns the path of the file.
 pub fn store_artifact(
     artifact: &CompiledArtifact,
-    address: &str,
-) -> Result<(), std::io::Error> {
-    // Construct the path to the deployments directory.
-    // Assuming the executable runs from somewhere like `xet-composer/backend/xet_composer_backend/target/debug/`
-    // or similar, `../../deployments/` should correctly point to `xet-composer/deployments/`.
-    // For robustness, it might be better to have this path configurable or determined
-    // based on the crate's manifest directory at compile time if possible,
-    // but for now, we'll use the relative path as specified.
-    let mut deployments_dir = PathBuf::from("."); // Start from current dir (where executable is assumed to run)
-    deployments_dir.push(".."); // Go up one level
-    deployments_dir.push(".."); // Go up another level
-    deployments_dir.push("deployments"); // Target the deployments directory
-
+    deployment: &DeploymentResult,
+    deployments_dir: &Path,
+    chain: &str,
+) -> Result<PathBuf, std::io::Error> {
     // Create the deployments directory if it doesn't exist.
-    fs::create_dir_all(&deployments_dir)?;
+    fs::create_dir_all(deployments_dir)?;
+
+    let address = checksum(&deployment.address);
 
     // Create an instance of StorableArtifactData.
     let data_to_store = StorableArtifactData {
         contract_name: artifact.contract_name.clone(),
-        abi: artifact.abi.clone(), // Assuming abi is String, as per previous subtask
+        source_name: artifact.source_name.clone(),
+        chain: chain.to_string(),
+        abi: artifact.abi.clone(),
         bytecode: artifact.bytecode.clone(),
-        address: address.to_string(),
+        link_references: artifact.link_references.clone(),
+        libraries: deployment.libraries.iter()
+            .map(|step| (step.library.clone(), checksum(&step.address)))
+            .collect(),
+        create2: deployment.create2.clone(),
+        deployed_bytecode: artifact.deployed_bytecode.clone(),
+        metadata: artifact.metadata.clone(),
+        storage_layout: artifact.storage_layout.clone(),
+        method_identifiers: artifact.method_identifiers.clone(),
+        immutable_references: artifact.immutable_references.clone(),
+        source_map: artifact.source_map.clone(),
+        deployed_source_map: artifact.deployed_source_map.clone(),
+        compiler_version: artifact.compiler_version.clone(),
+        address: address.clone(),
+        deployer: checksum(&deployment.deployer),
+        tx_hash: format!("{:?}", deployment.tx_hash),
+        block_number: deployment.block_number,
+        gas_used: deployment.gas_used,
         deployed_at: Utc::now().timestamp(),
+        compiler_settings: artifact.compiler_settings.clone(),
     };
 
-    // Construct the filename: <contract_name>-<address>.json.
-    // Basic cleaning for address: remove "0x" if present for a cleaner filename,
-    // though the requirement said "use the address as is" for now.
-    // Let's stick to "as is" for the moment.
-    let filename = format!("{}-{}.json", data_to_store.contract_name, address);
+    // Construct the filename: <chain>-<contract_name>-<address>.json. A CREATE2
+    // deployment, or one from the same key and nonce, has the same address on every
+    // chain, so the chain keeps them apart.
+    let filename = format!("{}-{}-{}.json", chain, data_to_store.contract_name, address);
     let file_path = deployments_dir.join(filename);
 
     // Serialize StorableArtifactData to a JSON string.
@@ -60,7 +96,44 @@ pub fn store_artifact(
     // Write the JSON string to the file.
     fs::write(&file_path, json_string)?;
 
-    Ok(())
+    Ok(file_path)
+}
+
+/// The fields of a stored artifact needed to recognise an earlier deployment.
+/// Artifacts written before chains were recorded have no `chain` and never match.
+#[derive(Deserialize)]
+struct StoredDeployment {
+    #[serde(default)]
+    chain: String,
+    contract_name: String,
+    #[serde(default)]
+    source_name: String,
+    bytecode: String,
+    address: String,
+    deployed_at: i64,
+}
+
+/// Address of the most recent deployment of `artifact` on `chain` found in
+/// `deployments_dir`. Only a deployment of identical bytecode counts, so a library that
+/// changed in any way is deployed afresh.
+pub fn find_deployed(deployments_dir: &Path, chain: &str, artifact: &CompiledArtifact) -> Option<Address> {
+    // Artifacts stored before the chain was part of the file name start with the contract.
+    let prefixes = [format!("{}-{}-", chain, artifact.contract_name), format!("{}-", artifact.contract_name)];
+    fs::read_dir(deployments_dir).ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            prefixes.iter().any(|prefix| file_name.starts_with(prefix.as_str()))
+        })
+        .filter_map(|entry| serde_json::from_slice::<StoredDeployment>(&fs::read(entry.path()).ok()?).ok())
+        .filter(|stored| {
+            stored.chain == chain
+                && stored.contract_name == artifact.contract_name
+                && stored.source_name == artifact.source_name
+                && stored.bytecode == artifact.bytecode
+        })
+        .max_by_key(|stored| stored.deployed_at)
+        .and_then(|stored| parse_address(&stored.address, false).ok())
 }
 
 // Example of how it might be used (for illustration, not part of the actual module usually)
@@ -72,11 +145,55 @@ fn _example() {
         abi: r#"[{"inputs":[],"name":"myFunction","outputs":[],"stateMutability":"nonpayable","type":"function"}]"#.to_string(),
         bytecode: "0x60806040...".to_string(),
     };
-    let dummy_address = "0x1234567890abcdef1234567890abcdef12345678";
+    let dummy_deployment = DeploymentResult {
+        address: "0x1234567890abcdef1234567890abcdef12345678".parse().unwrap(),
+        tx_hash: Default::default(),
+        block_number: 1,
+        gas_used: 21000,
+    };
 
-    match store_artifact(&dummy_artifact, dummy_address) {
+    match store_artifact(&dummy_artifact, &dummy_deployment, Path::new("../../deployments")) {
         Ok(()) => println!("Artifact stored successfully."),
         Err(e) => eprintln!("Failed to store artifact: {}", e),
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_deployed_matches_chain_and_bytecode() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = CompiledArtifact {
+            contract_name: "Math".to_string(),
+            source_name: "Vault.sol".to_string(),
+            bytecode: "6000".to_string(),
+            ..Default::default()
+        };
+        let deployment = |byte: u8| DeploymentResult {
+            address: Address::repeat_byte(byte),
+            deployer: Address::repeat_byte(0xde),
+            tx_hash: Default::default(),
+            block_number: 1,
+            gas_used: 21000,
+            libraries: Vec::new(),
+            create2: None,
+        };
+        store_artifact(&library, &deployment(0x11), dir.path(), "local").unwrap();
+
+        assert_eq!(find_deployed(dir.path(), "local", &library), Some(Address::repeat_byte(0x11)));
+        assert_eq!(find_deployed(dir.path(), "sepolia", &library), None);
+
+        // The same address on a second chain is stored beside the first, not over it.
+        let local = dir.path().join(format!("local-Math-{}.json", checksum(&Address::repeat_byte(0x11))));
+        let sepolia = store_artifact(&library, &deployment(0x11), dir.path(), "sepolia").unwrap();
+        assert_ne!(local, sepolia);
+        assert!(local.exists());
+        assert_eq!(find_deployed(dir.path(), "local", &library), Some(Address::repeat_byte(0x11)));
+        assert_eq!(find_deployed(dir.path(), "sepolia", &library), Some(Address::repeat_byte(0x11)));
+        let changed = CompiledArtifact { bytecode: "6001".to_string(), ..library.clone() };
+        assert_eq!(find_deployed(dir.path(), "local", &changed), None);
+    }
+}
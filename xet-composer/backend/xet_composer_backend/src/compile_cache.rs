@@ -0,0 +1,214 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::deploy_engine::Compilation;
+
+/// Hit/miss counters since startup, as reported by `GET /api/status`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub stores: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub max_entries: usize,
+}
+
+/// Content-addressed store of compilations, one JSON file per entry.
+///
+/// Entries are keyed by everything that determines solc's output, see [`cache_key`],
+/// including the contents of every imported file, so editing or upgrading a library in
+/// place misses the cache. Once more than `max_entries` files exist, the least recently
+/// used ones are removed.
+pub struct CompileCache {
+    dir: PathBuf,
+    max_entries: usize,
+    stats: Mutex<CacheStats>,
+}
+
+/// Hashes the standard-JSON input (source, remappings and every compiler setting), the
+/// contract name, the import base path, the identity of the compiler binary and the
+/// files solc reads from disk for the imports, by path and contents.
+pub fn cache_key(
+    input_json: &[u8],
+    contract_name: &str,
+    base_path: &Path,
+    solc_identity: &str,
+    imported_files: &BTreeMap<PathBuf, Vec<u8>>,
+) -> String {
+    let mut hasher = Sha256::new();
+    let mut update = |part: &[u8]| {
+        // Length-prefixed so that moving bytes between parts changes the key.
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    };
+    for part in [input_json, contract_name.as_bytes(), base_path.to_string_lossy().as_bytes(), solc_identity.as_bytes()] {
+        update(part);
+    }
+    for (path, contents) in imported_files {
+        update(path.to_string_lossy().as_bytes());
+        update(contents);
+    }
+    hex::encode(hasher.finalize())
+}
+
+impl CompileCache {
+    pub fn new(dir: PathBuf, max_entries: usize) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let cache = Self { dir, max_entries, stats: Mutex::new(CacheStats::default()) };
+        let entries = cache.entries().len();
+        *cache.stats.lock().unwrap() = CacheStats { entries, max_entries, ..CacheStats::default() };
+        Ok(cache)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Returns the cached compilation for `key`, if any. Unreadable entries count as misses
+    /// and are removed.
+    pub fn get(&self, key: &str) -> Option<Compilation> {
+        let path = self.entry_path(key);
+        let compilation = fs::read(&path).ok().and_then(|raw| match serde_json::from_slice(&raw) {
+            Ok(artifact) => Some(artifact),
+            Err(e) => {
+                eprintln!("Discarding unreadable compile cache entry {}: {}", path.display(), e);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        });
+
+        let mut stats = self.stats.lock().unwrap();
+        if compilation.is_some() {
+            stats.hits += 1;
+            // The modification time doubles as the last-used time for eviction.
+            if let Ok(file) = fs::File::options().append(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        } else {
+            stats.misses += 1;
+        }
+        compilation
+    }
+
+    /// Stores `compilation` under `key` and evicts the least recently used entries beyond
+    /// `max_entries`. Failures are logged; the cache is only an optimisation.
+    pub fn put(&self, key: &str, compilation: &Compilation) {
+        let path = self.entry_path(key);
+        // Written to a temporary file first so that concurrent readers never see a partial entry.
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        let written = serde_json::to_vec(compilation)
+            .map_err(std::io::Error::from)
+            .and_then(|json| fs::write(&tmp_path, json))
+            .and_then(|()| fs::rename(&tmp_path, &path));
+        if let Err(e) = written {
+            eprintln!("Could not write compile cache entry {}: {}", path.display(), e);
+            let _ = fs::remove_file(&tmp_path);
+            return;
+        }
+
+        let mut entries = self.entries();
+        let mut evicted = 0;
+        if entries.len() > self.max_entries {
+            entries.sort_by_key(|(_, modified)| *modified);
+            let excess = entries.len() - self.max_entries;
+            for (old_path, _) in entries.drain(..excess) {
+                if fs::remove_file(&old_path).is_ok() {
+                    evicted += 1;
+                }
+            }
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.stores += 1;
+        stats.evictions += evicted;
+        stats.entries = entries.len();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Entry files with their last-used time.
+    fn entries(&self) -> Vec<(PathBuf, SystemTime)> {
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        read_dir.filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deploy_engine::CompiledArtifact;
+    use std::time::Duration;
+
+    fn compilation(name: &str) -> Compilation {
+        let main = CompiledArtifact {
+            contract_name: name.to_string(),
+            bytecode: "6000".to_string(),
+            ..Default::default()
+        };
+        Compilation { main, libraries: Vec::new(), helpers: Vec::new(), diagnostics: Vec::new() }
+    }
+
+    #[test]
+    fn test_key_covers_every_input() {
+        let base = Path::new("/contracts");
+        let none = BTreeMap::new();
+        let key = cache_key(b"{}", "A", base, "0.8.19", &none);
+        assert_eq!(key, cache_key(b"{}", "A", base, "0.8.19", &none));
+        assert_ne!(key, cache_key(b"{ }", "A", base, "0.8.19", &none));
+        assert_ne!(key, cache_key(b"{}", "B", base, "0.8.19", &none));
+        assert_ne!(key, cache_key(b"{}", "A", Path::new("/other"), "0.8.19", &none));
+        assert_ne!(key, cache_key(b"{}", "A", base, "0.8.20", &none));
+
+        let library = |contents: &str| BTreeMap::from([(PathBuf::from("/lib/Ownable.sol"), contents.as_bytes().to_vec())]);
+        let imported = cache_key(b"{}", "A", base, "0.8.19", &library("contract Ownable {}"));
+        assert_ne!(key, imported);
+        assert_ne!(imported, cache_key(b"{}", "A", base, "0.8.19", &library("contract Ownable { uint x; }")));
+    }
+
+    #[test]
+    fn test_hits_misses_and_lru_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(dir.path().to_path_buf(), 2).unwrap();
+        assert!(cache.get("a").is_none());
+
+        cache.put("a", &compilation("A"));
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put("b", &compilation("B"));
+        std::thread::sleep(Duration::from_millis(20));
+        // Reading "a" makes "b" the least recently used entry.
+        assert_eq!(cache.get("a").unwrap().main.contract_name, "A");
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put("c", &compilation("C"));
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.stores, stats.evictions, stats.entries), (2, 2, 3, 1, 2));
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(dir.path().to_path_buf(), 10).unwrap();
+        fs::write(dir.path().join("bad.json"), "not json").unwrap();
+        assert!(cache.get("bad").is_none());
+        assert!(!dir.path().join("bad.json").exists());
+    }
+}
@@ -0,0 +1,577 @@
+use clap::{Parser, ValueEnum};
+use ethers::providers::{Http, Provider};
+use ethers::types::{Address, U256};
+use ethers::utils::parse_ether;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::address::parse_address;
+use crate::deploy_engine::{SignerSource, TrackingConfig};
+use crate::import_resolver::LibraryRoot;
+use crate::solc_standard_json::CompilerSettings;
+
+/// Config file read when `--config` is not given, if it exists in the working directory.
+pub const DEFAULT_CONFIG_FILE: &str = "xet-composer.toml";
+
+/// How deploy requests are checked before anything is compiled or sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KycMode {
+    /// Require a KYC block whose EIP-191 signature recovers to the claimed wallet.
+    Signature,
+    /// Skip KYC entirely. Only meant for local development.
+    Disabled,
+}
+
+/// Command-line flags. Each flag can also be set through the environment variable
+/// shown in `--help`; flags win over the environment, which wins over the config file.
+#[derive(Parser, Debug, Default)]
+#[command(name = "xet_composer_backend", about = "XET Composer backend server")]
+pub struct CliArgs {
+    /// TOML config file [default: xet-composer.toml, if present]
+    #[arg(long, env = "XET_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address the HTTP server binds to, e.g. 127.0.0.1:8000
+    #[arg(long, env = "XET_BIND_ADDRESS")]
+    pub bind_address: Option<SocketAddr>,
+    /// Directory holding the *.sol.tera templates
+    #[arg(long, env = "XET_CONTRACTS_DIR")]
+    pub contracts_dir: Option<PathBuf>,
+    /// Directory deployment artifacts are written to
+    #[arg(long, env = "XET_DEPLOYMENTS_DIR")]
+    pub deployments_dir: Option<PathBuf>,
+    /// Directory deployment jobs are persisted to
+    #[arg(long, env = "XET_JOBS_DIR")]
+    pub jobs_dir: Option<PathBuf>,
+    /// Library root imported under NAME (repeatable); replaces the configured list
+    #[arg(long = "library", env = "XET_LIBRARIES", value_delimiter = ',', value_name = "NAME[@VERSION]=PATH")]
+    pub libraries: Vec<String>,
+    /// Extra solc import remapping (repeatable); replaces the configured list
+    #[arg(long = "remapping", env = "XET_REMAPPINGS", value_delimiter = ',', value_name = "PREFIX=PATH")]
+    pub remappings: Vec<String>,
+    /// solc executable, used when no compiler cache is configured
+    #[arg(long, env = "SOLC_PATH")]
+    pub solc_path: Option<String>,
+    /// Directory of solc binaries with a list.json manifest; compilers are picked per pragma
+    #[arg(long, env = "XET_COMPILERS_DIR")]
+    pub compilers_dir: Option<PathBuf>,
+    /// Enable or disable the on-disk compilation cache
+    #[arg(long, env = "XET_COMPILE_CACHE")]
+    pub compile_cache: Option<bool>,
+    /// Directory of the compilation cache
+    #[arg(long, env = "XET_COMPILE_CACHE_DIR")]
+    pub compile_cache_dir: Option<PathBuf>,
+    /// Compilations kept before the least recently used are evicted
+    #[arg(long, env = "XET_COMPILE_CACHE_MAX_ENTRIES")]
+    pub compile_cache_max_entries: Option<usize>,
+    /// Enable or disable the solc optimizer
+    #[arg(long, env = "XET_OPTIMIZER")]
+    pub optimizer: Option<bool>,
+    /// Optimizer runs
+    #[arg(long, env = "XET_OPTIMIZER_RUNS")]
+    pub optimizer_runs: Option<u32>,
+    /// RPC endpoint for a named chain (repeatable)
+    #[arg(long = "chain-rpc", env = "XET_CHAIN_RPCS", value_delimiter = ',', value_name = "NAME=URL")]
+    pub chain_rpcs: Vec<String>,
+    /// Chain used when a deploy request does not name one
+    #[arg(long, env = "XET_DEFAULT_CHAIN")]
+    pub default_chain: Option<String>,
+    /// RPC endpoint for the default chain
+    #[arg(long, env = "RPC_URL")]
+    pub rpc_url: Option<String>,
+    /// KYC mode
+    #[arg(long, env = "XET_KYC_MODE", value_enum)]
+    pub kyc_mode: Option<KycMode>,
+    /// Local development mode; allows signing with a raw private key
+    #[arg(long, env = "XET_DEV_MODE")]
+    pub dev_mode: Option<bool>,
+}
+
+/// The `[compile_cache]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompileCacheConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+    pub max_entries: usize,
+}
+
+impl Default for CompileCacheConfig {
+    fn default() -> Self {
+        Self { enabled: true, dir: PathBuf::from("../../cache/compilations"), max_entries: 1000 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    pub rpc_url: String,
+    /// Addresses of libraries already deployed on this chain, keyed by bare library name
+    /// or by `file:Name`. Templates link against them instead of deploying their own copy.
+    #[serde(default)]
+    pub libraries: BTreeMap<String, String>,
+    /// CREATE2 factory for deployments with a salt; the deterministic deployment proxy
+    /// at 0x4e59b44847b379578588920cA78FbF26c0B4956C when unset.
+    #[serde(default)]
+    pub create2_factory: Option<String>,
+    /// Price transactions with a legacy gas price even if the chain supports EIP-1559.
+    #[serde(default)]
+    pub legacy_fees: bool,
+    /// Most a deployment may cost, in ether, e.g. "0.05". Deployments estimated above it
+    /// are rejected before anything is sent.
+    #[serde(default)]
+    pub max_deploy_cost: Option<String>,
+    /// Blocks a transaction must be in, counting its own, before it is final; 1 when unset.
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    /// Seconds a transaction may stay pending before it is replaced with higher fees.
+    /// Stuck transactions are waited for indefinitely when unset.
+    #[serde(default)]
+    pub stuck_timeout: Option<u64>,
+    /// Percentage each replacement raises the fees by; 20 when unset, at least 10.
+    #[serde(default)]
+    pub fee_bump_percent: Option<u64>,
+    /// Replacements sent for one transaction at most; 3 when unset.
+    #[serde(default)]
+    pub max_replacements: Option<u32>,
+}
+
+impl ChainConfig {
+    /// `libraries` with parsed addresses; entries that do not parse are rejected by validation.
+    pub fn library_addresses(&self) -> BTreeMap<String, Address> {
+        self.libraries.iter()
+            .filter_map(|(name, address)| Some((name.clone(), parse_address(address, false).ok()?)))
+            .collect()
+    }
+
+    /// `max_deploy_cost` in wei; a value that does not parse is rejected by validation.
+    pub fn max_deploy_cost_wei(&self) -> Option<U256> {
+        self.max_deploy_cost.as_deref().and_then(|cost| parse_ether(cost).ok())
+    }
+
+    /// How the deploy engine follows transactions on this chain.
+    pub fn tracking(&self) -> TrackingConfig {
+        let defaults = TrackingConfig::default();
+        TrackingConfig {
+            confirmations: self.confirmations.unwrap_or(defaults.confirmations),
+            stuck_timeout: self.stuck_timeout.map(Duration::from_secs),
+            fee_bump_percent: self.fee_bump_percent.unwrap_or(defaults.fee_bump_percent),
+            max_replacements: self.max_replacements.unwrap_or(defaults.max_replacements),
+            ..defaults
+        }
+    }
+}
+
+/// Backend settings, loaded once at startup and shared through the axum state.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub bind_address: SocketAddr,
+    pub contracts_dir: PathBuf,
+    pub deployments_dir: PathBuf,
+    /// Deployment jobs, one JSON file each, so they survive a restart.
+    pub jobs_dir: PathBuf,
+    /// Library roots; their remappings and `--allow-paths` are generated.
+    pub libraries: Vec<LibraryRoot>,
+    /// Remappings passed to solc in addition to those of `libraries`.
+    pub remappings: Vec<String>,
+    pub solc_path: String,
+    pub compilers_dir: Option<PathBuf>,
+    pub compile_cache: CompileCacheConfig,
+    /// Defaults for every compilation; template manifests and requests override them.
+    pub compiler: CompilerSettings,
+    pub default_chain: String,
+    pub chains: BTreeMap<String, ChainConfig>,
+    pub kyc_mode: KycMode,
+    /// Key deployments are signed with; see [`SignerSource`].
+    pub signer: Option<SignerSource>,
+    /// Enables settings only fit for local development, such as a raw private key signer.
+    pub dev_mode: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        let mut chains = BTreeMap::new();
+        chains.insert("local".to_string(), ChainConfig { rpc_url: "http://127.0.0.1:8545".to_string(), ..Default::default() });
+        Self {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            // Relative to the working directory, i.e. the crate root under `cargo run`.
+            contracts_dir: PathBuf::from("../../contracts"),
+            deployments_dir: PathBuf::from("../../deployments"),
+            jobs_dir: PathBuf::from("../../jobs"),
+            libraries: vec![LibraryRoot {
+                name: "@openzeppelin/contracts".to_string(),
+                path: PathBuf::from("lib/openzeppelin-repo/contracts"),
+                version: None,
+            }],
+            remappings: Vec::new(),
+            solc_path: "solc".to_string(),
+            compilers_dir: None,
+            compile_cache: CompileCacheConfig::default(),
+            compiler: CompilerSettings::defaults(),
+            default_chain: "local".to_string(),
+            chains,
+            kyc_mode: KycMode::Signature,
+            signer: None,
+            dev_mode: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Builds the configuration from defaults, the config file, the environment and
+    /// `args`, in increasing order of precedence, then validates it.
+    pub fn load(args: &CliArgs) -> Result<Self, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => Self::default(),
+        };
+        config.apply_args(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a TOML file. Relative paths in it are resolved against the file's directory.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let raw = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let mut config: AppConfig = toml::from_str(&raw).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.contracts_dir = base.join(&config.contracts_dir);
+        config.deployments_dir = base.join(&config.deployments_dir);
+        config.jobs_dir = base.join(&config.jobs_dir);
+        config.compilers_dir = config.compilers_dir.map(|dir| base.join(dir));
+        config.compile_cache.dir = base.join(&config.compile_cache.dir);
+        if let Some(signer) = &mut config.signer {
+            signer.resolve_paths(base);
+        }
+        Ok(config)
+    }
+
+    fn apply_args(&mut self, args: &CliArgs) -> Result<(), ConfigError> {
+        if let Some(bind_address) = args.bind_address {
+            self.bind_address = bind_address;
+        }
+        if let Some(dir) = &args.contracts_dir {
+            self.contracts_dir = dir.clone();
+        }
+        if let Some(dir) = &args.deployments_dir {
+            self.deployments_dir = dir.clone();
+        }
+        if let Some(dir) = &args.jobs_dir {
+            self.jobs_dir = dir.clone();
+        }
+        if !args.libraries.is_empty() {
+            self.libraries = args.libraries.iter()
+                .map(|spec| LibraryRoot::parse(spec))
+                .collect::<Result<_, _>>()
+                .map_err(ConfigError::Invalid)?;
+        }
+        if !args.remappings.is_empty() {
+            self.remappings = args.remappings.clone();
+        }
+        if let Some(solc_path) = &args.solc_path {
+            self.solc_path = solc_path.clone();
+        }
+        if let Some(dir) = &args.compilers_dir {
+            self.compilers_dir = Some(dir.clone());
+        }
+        if let Some(enabled) = args.compile_cache {
+            self.compile_cache.enabled = enabled;
+        }
+        if let Some(dir) = &args.compile_cache_dir {
+            self.compile_cache.dir = dir.clone();
+        }
+        if let Some(max_entries) = args.compile_cache_max_entries {
+            self.compile_cache.max_entries = max_entries;
+        }
+        if let Some(enabled) = args.optimizer {
+            self.compiler.optimizer = Some(enabled);
+        }
+        if let Some(runs) = args.optimizer_runs {
+            self.compiler.runs = Some(runs);
+        }
+        for entry in &args.chain_rpcs {
+            let (name, rpc_url) = entry.split_once('=')
+                .ok_or_else(|| ConfigError::Invalid(format!("--chain-rpc '{}' must have the form NAME=URL", entry)))?;
+            self.chains.entry(name.to_string()).or_default().rpc_url = rpc_url.to_string();
+        }
+        if let Some(chain) = &args.default_chain {
+            self.default_chain = chain.clone();
+        }
+        if let Some(rpc_url) = &args.rpc_url {
+            self.chains.entry(self.default_chain.clone()).or_default().rpc_url = rpc_url.clone();
+        }
+        if let Some(mode) = args.kyc_mode {
+            self.kyc_mode = mode;
+        }
+        if let Some(dev_mode) = args.dev_mode {
+            self.dev_mode = dev_mode;
+        }
+        Ok(())
+    }
+
+    /// Checks every setting, canonicalizes the contracts dir and creates the deployments dir.
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        self.contracts_dir = self.contracts_dir.canonicalize().map_err(|e| {
+            ConfigError::Invalid(format!("contracts_dir {} is not accessible: {}", self.contracts_dir.display(), e))
+        })?;
+        if !self.contracts_dir.is_dir() {
+            return Err(ConfigError::Invalid(format!("contracts_dir {} is not a directory", self.contracts_dir.display())));
+        }
+        fs::create_dir_all(&self.deployments_dir).map_err(|e| {
+            ConfigError::Invalid(format!("deployments_dir {} cannot be created: {}", self.deployments_dir.display(), e))
+        })?;
+
+        if self.solc_path.trim().is_empty() {
+            return Err(ConfigError::Invalid("solc_path cannot be empty".to_string()));
+        }
+        if let Some(dir) = &self.compilers_dir {
+            let manifest = dir.join(crate::solc_versions::MANIFEST_FILE);
+            if !manifest.is_file() {
+                return Err(ConfigError::Invalid(format!("compilers_dir {} has no {}", dir.display(), manifest.display())));
+            }
+        }
+        if self.compile_cache.enabled && self.compile_cache.max_entries == 0 {
+            return Err(ConfigError::Invalid("compile_cache.max_entries must be at least 1".to_string()));
+        }
+        let mut names = std::collections::HashSet::new();
+        for library in &self.libraries {
+            let name = library.name.trim_end_matches('/');
+            if name.is_empty() || name.contains('=') {
+                return Err(ConfigError::Invalid(format!("library name '{}' must be a non-empty import prefix", library.name)));
+            }
+            if !names.insert(name) {
+                return Err(ConfigError::Invalid(format!("library '{}' is configured twice", name)));
+            }
+            // Only a warning: imports from a missing library are reported per request.
+            let path = self.contracts_dir.join(&library.path);
+            if !path.is_dir() {
+                eprintln!("Library {} not found at {}; imports from it will not resolve.", name, path.display());
+            }
+        }
+        for remapping in &self.remappings {
+            match remapping.split_once('=') {
+                Some((prefix, target)) if !prefix.is_empty() && !target.is_empty() => {}
+                _ => return Err(ConfigError::Invalid(format!("remapping '{}' must have the form PREFIX=PATH", remapping))),
+            }
+        }
+
+        if !self.chains.contains_key(&self.default_chain) {
+            return Err(ConfigError::Invalid(format!(
+                "default_chain '{}' has no entry in [chains] (configured: {})",
+                self.default_chain,
+                self.chains.keys().cloned().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        for (name, chain) in &self.chains {
+            Provider::<Http>::try_from(chain.rpc_url.as_str()).map_err(|e| {
+                ConfigError::Invalid(format!("chains.{}.rpc_url '{}' is not a valid URL: {}", name, chain.rpc_url, e))
+            })?;
+            if let Some(factory) = &chain.create2_factory {
+                parse_address(factory, false).map_err(|e| {
+                    ConfigError::Invalid(format!("chains.{}.create2_factory '{}' is not a valid address: {}", name, factory, e))
+                })?;
+            }
+            if let Some(cost) = &chain.max_deploy_cost {
+                parse_ether(cost).map_err(|e| {
+                    ConfigError::Invalid(format!("chains.{}.max_deploy_cost '{}' is not an amount of ether: {}", name, cost, e))
+                })?;
+            }
+            if chain.confirmations == Some(0) {
+                return Err(ConfigError::Invalid(format!("chains.{}.confirmations must be at least 1", name)));
+            }
+            if chain.fee_bump_percent.is_some_and(|percent| percent < 10) {
+                return Err(ConfigError::Invalid(format!(
+                    "chains.{}.fee_bump_percent must be at least 10, or nodes reject the replacements", name
+                )));
+            }
+            for (library, address) in &chain.libraries {
+                parse_address(address, false).map_err(|e| {
+                    ConfigError::Invalid(format!("chains.{}.libraries.{} '{}' is not a valid address: {}", name, library, address, e))
+                })?;
+            }
+        }
+        if let Some(signer) = &self.signer {
+            signer.check(self.dev_mode).map_err(|e| ConfigError::Invalid(format!("signer: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contracts_dir() -> String {
+        format!("{}/../../contracts", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn write_config(dir: &Path, contents: &str) -> PathBuf {
+        let path = dir.join("xet-composer.toml");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_file_then_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(dir.path(), &format!(r#"
+            bind_address = "0.0.0.0:9000"
+            contracts_dir = "{}"
+            deployments_dir = "out/deployments"
+            jobs_dir = "out/jobs"
+            default_chain = "sepolia"
+
+            [compiler]
+            optimizer = true
+            runs = 1000
+            evm_version = "paris"
+
+            [signer]
+            type = "keystore"
+            path = "keys/deployer.json"
+            password_env = "DEPLOYER_KEYSTORE_PASSWORD"
+
+            [chains.sepolia]
+            rpc_url = "https://sepolia.example.org"
+            max_deploy_cost = "0.05"
+            confirmations = 3
+            stuck_timeout = 120
+
+            [chains.sepolia.libraries]
+            "Vault.sol:Math" = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+        "#, contracts_dir()));
+
+        // Built directly rather than parsed, so variables like RPC_URL in the test
+        // environment do not leak in.
+        let args = CliArgs {
+            config: Some(path),
+            optimizer_runs: Some(50),
+            chain_rpcs: vec!["local=http://127.0.0.1:9545".to_string()],
+            ..CliArgs::default()
+        };
+        let config = AppConfig::load(&args).unwrap();
+
+        assert_eq!(config.bind_address, "0.0.0.0:9000".parse().unwrap());
+        assert_eq!(config.compiler.runs, Some(50));
+        assert_eq!(config.compiler.evm_version, Some(crate::solc_standard_json::EvmVersion::Paris));
+        assert!(config.deployments_dir.starts_with(dir.path()));
+        assert!(config.deployments_dir.is_dir());
+        assert_eq!(config.jobs_dir, dir.path().join("out/jobs"));
+        assert_eq!(config.default_chain, "sepolia");
+        assert_eq!(config.chains["sepolia"].rpc_url, "https://sepolia.example.org");
+        assert_eq!(
+            config.chains["sepolia"].library_addresses()["Vault.sol:Math"],
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse().unwrap()
+        );
+        assert_eq!(config.chains["sepolia"].max_deploy_cost_wei(), Some(U256::from(50_000_000_000_000_000u64)));
+        let tracking = config.chains["sepolia"].tracking();
+        assert_eq!((tracking.confirmations, tracking.stuck_timeout), (3, Some(Duration::from_secs(120))));
+        assert_eq!(config.chains["local"].tracking(), TrackingConfig::default());
+        assert_eq!(config.chains["local"].rpc_url, "http://127.0.0.1:9545");
+        assert!(matches!(
+            &config.signer,
+            Some(SignerSource::Keystore { path, .. }) if *path == dir.path().join("keys/deployer.json")
+        ));
+    }
+
+    #[test]
+    fn test_flags_parse() {
+        let args = CliArgs::try_parse_from(["xet_composer_backend", "--kyc-mode", "disabled", "--remapping", "a/=b/"]).unwrap();
+        assert_eq!(args.kyc_mode, Some(KycMode::Disabled));
+        assert_eq!(args.remappings, vec!["a/=b/"]);
+        assert!(CliArgs::try_parse_from(["xet_composer_backend", "--kyc-mode", "maybe"]).is_err());
+    }
+
+    #[test]
+    fn test_example_config_parses() {
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("xet-composer.example.toml");
+        let config = AppConfig::from_file(&example).unwrap();
+        assert_eq!(config.default_chain, "local");
+        assert!(!config.dev_mode);
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(dir.path(), "bind_adress = \"127.0.0.1:8000\"\n");
+        let err = AppConfig::from_file(&path).unwrap_err();
+        assert!(err.to_string().contains("bind_adress"), "{}", err);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = AppConfig {
+            contracts_dir: PathBuf::from(contracts_dir()),
+            deployments_dir: dir.path().join("deployments"),
+            ..AppConfig::default()
+        };
+        config.default_chain = "mainnet".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("default_chain 'mainnet'"), "{}", err);
+
+        config.default_chain = "local".to_string();
+        config.remappings = vec!["@openzeppelin/".to_string()];
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("PREFIX=PATH"), "{}", err);
+
+        config.remappings.clear();
+        config.libraries.push(config.libraries[0].clone());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("configured twice"), "{}", err);
+
+        config.libraries.clear();
+        config.chains.get_mut("local").unwrap().libraries.insert("Math".to_string(), "0x1234".to_string());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("chains.local.libraries.Math"), "{}", err);
+
+        config.chains.get_mut("local").unwrap().libraries.clear();
+        config.chains.get_mut("local").unwrap().create2_factory = Some("factory".to_string());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("chains.local.create2_factory"), "{}", err);
+
+        config.chains.get_mut("local").unwrap().create2_factory = None;
+        config.chains.get_mut("local").unwrap().max_deploy_cost = Some("a lot".to_string());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("chains.local.max_deploy_cost"), "{}", err);
+
+        config.chains.get_mut("local").unwrap().max_deploy_cost = None;
+        config.chains.get_mut("local").unwrap().fee_bump_percent = Some(5);
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("chains.local.fee_bump_percent"), "{}", err);
+
+        config.chains.get_mut("local").unwrap().fee_bump_percent = None;
+        config.signer = Some(SignerSource::PrivateKey { key_env: Some("DEPLOYER_PRIVATE_KEY".to_string()), key_file: None });
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("dev mode"), "{}", err);
+        config.dev_mode = true;
+        assert!(config.validate().is_ok());
+
+        config.contracts_dir = dir.path().join("missing");
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().starts_with("contracts_dir"), "{}", err);
+    }
+}
@@ -1,22 +1,81 @@
-use ethers::prelude::*; // Basic ethers types, actual deployment later
+use ethers::abi::Abi;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use tokio::sync::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::process::{Command, Output};
-use std::path::Path; // Keep Path
-use std::fs;
-use tempfile::{NamedTempFile, Builder}; // Added Builder for tempdir
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::collections::BTreeMap;
 use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::abi_value::{json_to_token, AbiParamNames, ValueError};
+use crate::solc_standard_json::{
+    BytecodeRange, CompilerSettings, ContractKind, ContractOutput, Diagnostic, LinkReferences, Severity,
+    StandardJsonInput, StandardJsonOutput,
+};
+use crate::solc_versions::{pragma_requirements, CompilerCache};
+use crate::compile_cache::{cache_key, CompileCache};
+use crate::import_resolver::{ImportResolver, UnresolvedImport};
+use crate::nonce_manager::NonceManager;
 
 // Error type for this module
 #[derive(Debug)]
 pub enum DeployError {
     IoError(std::io::Error),
     SolcError(String),
-    EthersError(String), // Placeholder for actual ethers errors
+    EthersError(String), // Provider / RPC errors surfaced by ethers
+    SignerError(String), // Missing or invalid deployer key
+    NoReceipt(String), // Transaction dropped or receipt without a contract address
+    InvalidBytecode(String),
+    ConstructorArgError { param: String, reason: String }, // `param` is the path of the offending value, e.g. `recipients[2]`
     JsonError(serde_json::Error),
-    CompilationFailed(String), // Kept this, but SolcError is more specific for compilation
+    CompilationFailed(Vec<Diagnostic>), // solc ran but reported errors; SolcError is for solc itself failing
+    InvalidPragma(String), // `pragma solidity` range that cannot be parsed
+    NoMatchingCompiler(String), // No verified compiler in the cache satisfies the pragma
+    UnresolvedImports(Vec<UnresolvedImport>), // Imports that resolve to no file; checked before solc runs
+    LinkError(String), // A library the bytecode links against has no address and cannot be deployed
+    Create2Error(String), // No factory on the chain, or the predicted address is already taken
+    CostLimitExceeded(String), // The estimated cost is above the chain's max_deploy_cost
     NoAbiFound(String),
     NoBytecodeFound(String),
-    TempDirError(std::io::Error), // For tempdir creation errors
+}
+
+impl std::fmt::Display for DeployError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeployError::IoError(e) => write!(f, "I/O error: {}", e),
+            DeployError::SolcError(msg) => write!(f, "solc error: {}", msg),
+            DeployError::EthersError(msg) => write!(f, "RPC error: {}", msg),
+            DeployError::SignerError(msg) => write!(f, "Signer error: {}", msg),
+            DeployError::NoReceipt(msg) => write!(f, "Deployment not confirmed: {}", msg),
+            DeployError::InvalidBytecode(msg) => write!(f, "Invalid bytecode: {}", msg),
+            DeployError::JsonError(e) => write!(f, "JSON error: {}", e),
+            DeployError::CompilationFailed(diagnostics) => {
+                let errors: Vec<&str> = diagnostics.iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .map(|d| d.formatted_message.as_deref().unwrap_or(&d.message))
+                    .collect();
+                write!(f, "Compilation failed: {}", errors.join("\n"))
+            }
+            DeployError::InvalidPragma(msg) => write!(f, "Invalid pragma: {}", msg),
+            DeployError::NoMatchingCompiler(msg) => write!(f, "No matching compiler: {}", msg),
+            DeployError::UnresolvedImports(imports) => {
+                let paths: Vec<&str> = imports.iter().map(|i| i.path.as_str()).collect();
+                write!(f, "Unresolved imports: {}", paths.join(", "))
+            }
+            DeployError::LinkError(msg) => write!(f, "Library linking failed: {}", msg),
+            DeployError::Create2Error(msg) => write!(f, "CREATE2 deployment failed: {}", msg),
+            DeployError::CostLimitExceeded(msg) => write!(f, "Deployment too expensive: {}", msg),
+            DeployError::NoAbiFound(msg) => write!(f, "No ABI found: {}", msg),
+            DeployError::NoBytecodeFound(msg) => write!(f, "No bytecode found: {}", msg),
+            DeployError::ConstructorArgError { param, reason } => {
+                write!(f, "Invalid constructor argument `{}`: {}", param, reason)
+            }
+        }
+    }
 }
 
 impl From<std::io::Error> for DeployError {
@@ -32,57 +91,678 @@ impl From<serde_json::Error> for DeployError {
     }
 }
 
+impl From<ValueError> for DeployError {
+    fn from(err: ValueError) -> DeployError {
+        DeployError::ConstructorArgError { param: err.path, reason: err.reason }
+    }
+}
+
+/// Derivation path prefix of the first Ethereum account, `m/44'/60'/0'/0`; the account
+/// index is appended.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0";
+
+/// Where the deployer's signing key comes from, the `[signer]` table of the config.
+/// Secrets are only named here, by environment variable or file, and read when the key is
+/// loaded, so a `SignerSource` is safe to log and the key itself is never serialized.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SignerSource {
+    /// An encrypted JSON keystore (Web3 Secret Storage), unlocked with a password.
+    Keystore {
+        path: PathBuf,
+        password_env: Option<String>,
+        password_file: Option<PathBuf>,
+    },
+    /// A BIP-39 mnemonic; the key is derived at `derivation_path/index`.
+    Mnemonic {
+        mnemonic_env: Option<String>,
+        mnemonic_file: Option<PathBuf>,
+        #[serde(default = "default_derivation_path")]
+        derivation_path: String,
+        #[serde(default)]
+        index: u32,
+    },
+    /// A raw hex private key. Refused unless the server runs in dev mode.
+    PrivateKey {
+        key_env: Option<String>,
+        key_file: Option<PathBuf>,
+    },
+}
+
+fn default_derivation_path() -> String {
+    DEFAULT_DERIVATION_PATH.to_string()
+}
+
+impl SignerSource {
+    /// Checks the source without reading any secret: exactly one of the env/file options
+    /// is set, and a raw private key is only accepted when `dev_mode` is on.
+    pub fn check(&self, dev_mode: bool) -> Result<(), String> {
+        let (what, env, file) = match self {
+            SignerSource::Keystore { password_env, password_file, .. } => ("password", password_env, password_file),
+            SignerSource::Mnemonic { mnemonic_env, mnemonic_file, .. } => ("mnemonic", mnemonic_env, mnemonic_file),
+            SignerSource::PrivateKey { key_env, key_file } => {
+                if !dev_mode {
+                    return Err("a raw private key signer is only allowed in dev mode (--dev-mode); use a keystore or mnemonic".to_string());
+                }
+                ("key", key_env, key_file)
+            }
+        };
+        match (env, file) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(format!("exactly one of {0}_env and {0}_file must be set", what)),
+        }
+    }
+
+    /// Resolves relative keystore and secret file paths against `base`.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        let files = match self {
+            SignerSource::Keystore { path, password_file, .. } => vec![Some(path), password_file.as_mut()],
+            SignerSource::Mnemonic { mnemonic_file, .. } => vec![mnemonic_file.as_mut()],
+            SignerSource::PrivateKey { key_file, .. } => vec![key_file.as_mut()],
+        };
+        for file in files.into_iter().flatten() {
+            *file = base.join(&*file);
+        }
+    }
+
+    /// Reads the secret and unlocks the key. Error messages never contain the secret.
+    pub fn load(&self, dev_mode: bool) -> Result<LocalWallet, DeployError> {
+        self.check(dev_mode).map_err(DeployError::SignerError)?;
+        match self {
+            SignerSource::Keystore { path, password_env, password_file } => {
+                let password = read_secret("keystore password", password_env, password_file)?;
+                LocalWallet::decrypt_keystore(path, password.trim_end_matches(['\r', '\n'])).map_err(|e| {
+                    DeployError::SignerError(format!("cannot unlock keystore {}: {}", path.display(), e))
+                })
+            }
+            SignerSource::Mnemonic { mnemonic_env, mnemonic_file, derivation_path, index } => {
+                let phrase = read_secret("mnemonic", mnemonic_env, mnemonic_file)?;
+                let path = format!("{}/{}", derivation_path.trim_end_matches('/'), index);
+                MnemonicBuilder::<coins_bip39::English>::default()
+                    .phrase(phrase.trim())
+                    .derivation_path(&path)
+                    .and_then(|builder| builder.build())
+                    // The error may quote words of the phrase, so it is not passed on.
+                    .map_err(|_| DeployError::SignerError(format!("cannot derive a key at {} from the mnemonic", path)))
+            }
+            SignerSource::PrivateKey { key_env, key_file } => {
+                read_secret("private key", key_env, key_file)?.trim().parse::<LocalWallet>()
+                    .map_err(|_| DeployError::SignerError("the private key is not a valid secp256k1 key".to_string()))
+            }
+        }
+    }
+}
+
+/// Reads a secret from the environment variable `env` or from `file`.
+fn read_secret(what: &str, env: &Option<String>, file: &Option<PathBuf>) -> Result<String, DeployError> {
+    match (env, file) {
+        (Some(var), _) => std::env::var(var)
+            .map_err(|_| DeployError::SignerError(format!("{} environment variable {} is not set", what, var))),
+        (None, Some(file)) => std::fs::read_to_string(file)
+            .map_err(|e| DeployError::SignerError(format!("cannot read {} from {}: {}", what, file.display(), e))),
+        (None, None) => Err(DeployError::SignerError(format!("no {} configured", what))),
+    }
+}
+
 pub struct DeployEngine {
     solc_executable: String, // Modified field name
+    rpc_url: String,
+    deployer: Option<LocalWallet>,
+    compiler_settings: CompilerSettings, // Defaults beneath the manifest and request settings
+    compilers: Option<Arc<CompilerCache>>, // When set, replaces `solc_executable`
+    compile_cache: Option<Arc<CompileCache>>,
+    libraries: BTreeMap<String, Address>, // Already deployed libraries, by `file:Name` or bare name
+    create2_factory: Address,
+    legacy_fees: bool, // Price with gasPrice even if the chain reports a base fee
+    max_deploy_cost: Option<U256>, // In wei, over every transaction of a deployment
+    tracking: TrackingConfig,
+    nonces: Arc<NonceManager>, // Shared by the engines of every chain
+}
+
+/// How a sent transaction is followed until it is final.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackingConfig {
+    /// Blocks, counting the one that includes the transaction, before it is final.
+    pub confirmations: u64,
+    pub poll_interval: Duration,
+    /// A transaction still pending after this long is replaced by one with the same nonce
+    /// and higher fees. `None` waits for it indefinitely.
+    pub stuck_timeout: Option<Duration>,
+    /// How much each replacement raises the fees, in percent. Nodes require at least 10.
+    pub fee_bump_percent: u64,
+    pub max_replacements: u32,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            confirmations: 1,
+            poll_interval: Duration::from_secs(2),
+            stuck_timeout: None,
+            fee_bump_percent: 20,
+            max_replacements: 3,
+        }
+    }
+}
+
+/// The deterministic deployment proxy at the same address on most chains, and
+/// predeployed by anvil. Its calldata is a 32-byte salt followed by the init code.
+pub const DEFAULT_CREATE2_FACTORY: &str = "0x4e59b44847b379578588920cA78FbF26c0B4956C";
+
+/// How a contract is, or would be, deployed through the CREATE2 factory.
+#[derive(Debug, Clone, Serialize)]
+pub struct Create2Info {
+    #[serde(serialize_with = "crate::address::serialize_checksummed")]
+    pub factory: Address,
+    pub salt: H256,
+    pub init_code_hash: H256, // keccak256 of the linked creation code with constructor args
+}
+
+/// Where a CREATE2 deployment would put the main contract of a compilation.
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressPrediction {
+    #[serde(serialize_with = "crate::address::serialize_checksummed")]
+    pub address: Address,
+    pub create2: Create2Info,
+    pub libraries: Vec<LinkStep>,
+}
+
+/// Reads a CREATE2 salt: a `0x`-prefixed 32-byte hex value is used as is, and any other
+/// string, e.g. `vesting-alice-2026`, is hashed with keccak256 into one.
+pub fn parse_salt(input: &str) -> Result<H256, String> {
+    match input.strip_prefix("0x") {
+        Some(digits) => {
+            let bytes = hex::decode(digits).map_err(|e| format!("salt '{}' is not valid hex: {}", input, e))?;
+            if bytes.len() != 32 {
+                return Err(format!("salt '{}' must be 32 bytes, got {}", input, bytes.len()));
+            }
+            Ok(H256::from_slice(&bytes))
+        }
+        None if input.is_empty() => Err("salt cannot be empty".to_string()),
+        None => Ok(H256::from(ethers::utils::keccak256(input.as_bytes()))),
+    }
+}
+
+/// How the fees of a deployment's transactions are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FeeStrategy {
+    /// Priority fee at the 90th percentile of recent blocks; 125% of the gas price on legacy chains.
+    Fast,
+    /// 50th percentile; the gas price on legacy chains.
+    #[default]
+    Normal,
+    /// 10th percentile; 90% of the gas price on legacy chains.
+    Slow,
+    /// Fees in wei. On legacy chains `max_fee_per_gas` is the gas price.
+    Fixed {
+        #[serde(serialize_with = "serialize_wei", deserialize_with = "deserialize_wei")]
+        max_fee_per_gas: U256,
+        #[serde(serialize_with = "serialize_wei", deserialize_with = "deserialize_wei")]
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl FeeStrategy {
+    /// Percentile of recent priority fees to pay, and percentage of the legacy gas price.
+    fn percentiles(&self) -> (f64, u64) {
+        match self {
+            FeeStrategy::Fast => (90.0, 125),
+            FeeStrategy::Normal | FeeStrategy::Fixed { .. } => (50.0, 100),
+            FeeStrategy::Slow => (10.0, 90),
+        }
+    }
+}
+
+/// Fees the transactions of a deployment are sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeParams {
+    Eip1559 {
+        /// Base fee of the next block, for reference.
+        #[serde(serialize_with = "serialize_wei")]
+        base_fee_per_gas: U256,
+        #[serde(serialize_with = "serialize_wei")]
+        max_fee_per_gas: U256,
+        #[serde(serialize_with = "serialize_wei")]
+        max_priority_fee_per_gas: U256,
+    },
+    Legacy {
+        #[serde(serialize_with = "serialize_wei")]
+        gas_price: U256,
+    },
+}
+
+impl FeeParams {
+    /// The most a unit of gas can cost.
+    pub fn max_price_per_gas(&self) -> U256 {
+        match self {
+            FeeParams::Eip1559 { max_fee_per_gas, .. } => *max_fee_per_gas,
+            FeeParams::Legacy { gas_price } => *gas_price,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Gas needed by one transaction of a deployment.
+#[derive(Debug, Clone, Serialize)]
+pub struct TxEstimate {
+    pub contract: String,
+    pub gas_limit: u64,
+}
+
+/// What a deployment would cost: a transaction per library still to deploy, then the
+/// main contract, all priced with the same `fees`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GasEstimate {
+    pub gas_limit: u64, // Sum over `transactions`
+    pub fees: FeeParams,
+    /// `gas_limit` times the maximum price per gas, i.e. an upper bound.
+    #[serde(serialize_with = "serialize_wei")]
+    pub total_cost_wei: U256,
+    pub total_cost_eth: String,
+    pub transactions: Vec<TxEstimate>,
+}
+
+/// Wei amounts are serialized as decimal strings, which JSON numbers cannot hold exactly.
+fn serialize_wei<S: serde::Serializer>(wei: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&wei.to_string())
+}
+
+/// Reads a wei amount from a decimal string, a `0x` hex string or a JSON number.
+fn deserialize_wei<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64().map(U256::from)
+            .ok_or_else(|| serde::de::Error::custom(format!("{} is not a wei amount", n))),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(digits) => U256::from_str_radix(digits, 16).map_err(serde::de::Error::custom),
+            None => U256::from_dec_str(&s).map_err(serde::de::Error::custom),
+        },
+        other => Err(serde::de::Error::custom(format!("{} is not a wei amount", other))),
+    }
+}
+
+/// Options of [`DeployEngine::deploy_linked`] and [`DeployEngine::estimate_deployment`].
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    /// Deploys the main contract through the CREATE2 factory with this salt.
+    pub salt: Option<H256>,
+    pub fee: FeeStrategy,
+    /// Receives a [`DeployEvent`] at each step of every transaction.
+    pub events: Option<UnboundedSender<DeployEvent>>,
+}
+
+/// Progress of a deployment. Each transaction, for a library or the main contract, goes
+/// through these in order; `contract` is its fully qualified name.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DeployEvent {
+    /// A library was resolved to an address, or deployed.
+    Linked { step: LinkStep },
+    Signing { contract: String },
+    /// Sent and accepted by the node. The contract will be at `address` once mined.
+    Broadcasting {
+        contract: String,
+        tx_hash: TxHash,
+        nonce: U256,
+        #[serde(serialize_with = "crate::address::serialize_checksummed")]
+        address: Address,
+    },
+    /// Waiting to be mined.
+    Confirming { contract: String, tx_hash: TxHash },
+    /// Included in a block, and `confirmations` deep out of the `required` ones.
+    Confirmation { contract: String, tx_hash: TxHash, block_number: u64, confirmations: u64, required: u64 },
+    /// The block that included the transaction left the chain; it is followed again.
+    Reorged { contract: String, tx_hash: TxHash, block_number: u64 },
+    /// Pending for too long, so `replaced` was sent again with the same nonce and higher fees.
+    Replaced { contract: String, replaced: TxHash, tx_hash: TxHash, nonce: U256 },
+    /// Final: mined with the required confirmations.
+    Confirmed { contract: String, tx_hash: TxHash, block_number: u64 },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompiledArtifact {
     pub contract_name: String,
-    pub abi: String,
-    pub bytecode: String, // Hex string of bytecode
+    pub source_name: String, // Source unit that defines the contract, e.g. "TokenVesting.sol" or an imported file
+    pub kind: ContractKind,
+    pub abi: Abi,
+    /// The ABI exactly as solc emitted it. Unlike `abi`, it keeps the names of tuple
+    /// components, which constructor encoding needs.
+    pub raw_abi: Value,
+    pub bytecode: String, // Hex string of bytecode; holds `__$...$__` placeholders until linked
+    pub deployed_bytecode: String, // Hex string of the runtime bytecode
+    pub link_references: LinkReferences, // Libraries the creation bytecode must be linked against
+    pub metadata: String, // solc's metadata JSON, verbatim, as needed for source verification
+    pub storage_layout: Value,
+    pub method_identifiers: BTreeMap<String, String>, // Signature -> selector, e.g. "release()" -> "86d1a69f"
+    pub immutable_references: BTreeMap<String, Vec<BytecodeRange>>,
+    pub source_map: String,
+    pub deployed_source_map: String,
+    pub compiler_version: Option<String>, // Long version from the metadata, e.g. "0.8.19+commit.7dd6d404"
+    pub compiler_settings: CompilerSettings, // Resolved settings the artifact was compiled with
+}
+
+impl CompiledArtifact {
+    /// Builds the artifact for `contract_name` in `source_name` from its entry in solc's output.
+    pub fn from_output(
+        source_name: &str,
+        contract_name: &str,
+        kind: ContractKind,
+        output: &ContractOutput,
+        compiler_settings: &CompilerSettings,
+    ) -> Result<Self, DeployError> {
+        let abi: Abi = serde_json::from_value(output.abi.clone())
+            .map_err(|e| DeployError::NoAbiFound(format!("solc produced an unreadable ABI for {}: {}", contract_name, e)))?;
+        let compiler_version = serde_json::from_str::<Value>(&output.metadata).ok()
+            .and_then(|metadata| metadata["compiler"]["version"].as_str().map(str::to_string));
+        Ok(CompiledArtifact {
+            contract_name: contract_name.to_string(),
+            source_name: source_name.to_string(),
+            kind,
+            abi,
+            raw_abi: output.abi.clone(),
+            bytecode: output.evm.bytecode.object.trim().to_string(),
+            deployed_bytecode: output.evm.deployed_bytecode.object.trim().to_string(),
+            link_references: output.evm.bytecode.link_references.clone(),
+            metadata: output.metadata.clone(),
+            storage_layout: output.storage_layout.clone(),
+            method_identifiers: output.evm.method_identifiers.clone(),
+            immutable_references: output.evm.deployed_bytecode.immutable_references.clone(),
+            source_map: output.evm.bytecode.source_map.clone(),
+            deployed_source_map: output.evm.deployed_bytecode.source_map.clone(),
+            compiler_version,
+            compiler_settings: compiler_settings.clone(),
+        })
+    }
+
+    /// The name solc uses in link references, e.g. `contracts/Math.sol:Math`.
+    pub fn qualified_name(&self) -> String {
+        format!("{}:{}", self.source_name, self.contract_name)
+    }
+}
+
+/// Every contract produced by compiling one rendered template, including those of the
+/// files it imports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Compilation {
+    /// The contract to deploy, as named by the template manifest.
+    pub main: CompiledArtifact,
+    /// Libraries, which have to be deployed or linked before contracts that call them.
+    pub libraries: Vec<CompiledArtifact>,
+    /// The remaining contracts, abstract contracts and interfaces.
+    pub helpers: Vec<CompiledArtifact>,
+    pub diagnostics: Vec<Diagnostic>, // Warnings and infos from a successful compilation
+}
+
+impl Compilation {
+    /// Sorts solc's output into the main contract, `main_contract` of `source_name`,
+    /// libraries and helpers.
+    pub fn from_output(
+        output: &StandardJsonOutput,
+        source_name: &str,
+        main_contract: &str,
+        diagnostics: Vec<Diagnostic>,
+        compiler_settings: &CompilerSettings,
+    ) -> Result<Self, DeployError> {
+        let mut main = None;
+        let mut libraries = Vec::new();
+        let mut helpers = Vec::new();
+        for (file, contracts) in &output.contracts {
+            for (name, contract) in contracts {
+                let kind = output.contract_kind(file, name);
+                let artifact = CompiledArtifact::from_output(file, name, kind, contract, compiler_settings)?;
+                if file == source_name && name == main_contract {
+                    main = Some(artifact);
+                } else if kind == ContractKind::Library {
+                    libraries.push(artifact);
+                } else {
+                    helpers.push(artifact);
+                }
+            }
+        }
+
+        let main = main.ok_or_else(|| {
+            let defined: Vec<&str> = output.contracts.get(source_name)
+                .map(|contracts| contracts.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            DeployError::NoAbiFound(format!(
+                "solc produced no contract named {} in {} (found: {})",
+                main_contract,
+                source_name,
+                if defined.is_empty() { "none".to_string() } else { defined.join(", ") }
+            ))
+        })?;
+        if main.bytecode.is_empty() {
+            return Err(DeployError::NoBytecodeFound(format!(
+                "solc produced no bytecode for {}; interfaces and abstract contracts cannot be deployed",
+                main_contract
+            )));
+        }
+        Ok(Self { main, libraries, helpers, diagnostics })
+    }
+}
+
+/// Outcome of a mined contract-creation transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeploymentResult {
+    pub address: Address,
+    /// Account that signed the creation transaction.
+    #[serde(serialize_with = "crate::address::serialize_checksummed")]
+    pub deployer: Address,
+    pub tx_hash: TxHash,
+    pub block_number: u64,
+    pub gas_used: u64,
+    /// Libraries linked into the bytecode, in the order they were resolved.
+    pub libraries: Vec<LinkStep>,
+    /// Set for deployments through the CREATE2 factory.
+    pub create2: Option<Create2Info>,
+}
+
+/// Libraries to link, dependencies first, with the address and its source where known.
+type LinkPlan<'a> = Vec<(&'a CompiledArtifact, Option<(Address, LibrarySource)>)>;
+
+/// Where the address of a linked library came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibrarySource {
+    Configured, // `libraries` of the chain's config
+    Stored, // A previous deployment in the deployments directory
+    Deployed, // Deployed as part of this deployment
+}
+
+/// One library linked into a deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkStep {
+    pub library: String, // Fully qualified, e.g. `Vault.sol:Math`
+    #[serde(serialize_with = "crate::address::serialize_checksummed")]
+    pub address: Address,
+    pub source: LibrarySource,
+    /// Set when the library was deployed as part of this deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<TxHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<u64>,
 }
 
 impl DeployEngine {
-    pub fn new(solc_executable: String) -> Self { // Modified
-        Self { solc_executable }
+    /// `rpc_url` is the JSON-RPC endpoint creation transactions are sent to,
+    /// e.g. `http://127.0.0.1:8545` for a local anvil node.
+    pub fn new(solc_executable: String, rpc_url: String) -> Self {
+        Self {
+            solc_executable,
+            rpc_url,
+            deployer: None,
+            compiler_settings: CompilerSettings::defaults(),
+            compilers: None,
+            compile_cache: None,
+            libraries: BTreeMap::new(),
+            create2_factory: DEFAULT_CREATE2_FACTORY.parse().expect("valid factory address"),
+            legacy_fees: false,
+            max_deploy_cost: None,
+            tracking: TrackingConfig::default(),
+            nonces: Arc::new(NonceManager::default()),
+        }
+    }
+
+    /// Sets the compiler settings used where neither the manifest nor the request set one.
+    pub fn with_compiler_settings(mut self, settings: CompilerSettings) -> Self {
+        self.compiler_settings = CompilerSettings::defaults().overlay(&settings);
+        self
+    }
+
+    /// Picks the compiler for each source from `compilers` according to its
+    /// `pragma solidity`, instead of always running `solc_executable`.
+    pub fn with_compiler_cache(mut self, compilers: Arc<CompilerCache>) -> Self {
+        self.compilers = Some(compilers);
+        self
+    }
+
+    /// Path of the solc binary to compile `source` with, and its verified SHA-256 if it
+    /// came from the compiler cache.
+    fn solc_for(&self, source: &str) -> Result<(String, Option<String>), DeployError> {
+        let Some(compilers) = &self.compilers else {
+            return Ok((self.solc_executable.clone(), None));
+        };
+        // Sources solang-parser cannot read go to the newest compiler, which reports the errors.
+        let requirements = pragma_requirements(source).map_err(DeployError::InvalidPragma)?.unwrap_or_default();
+        let compiler = compilers.select(&requirements).map_err(DeployError::NoMatchingCompiler)?;
+        println!("Using solc {} from {}", compiler.version, compiler.path.display());
+        Ok((compiler.path.to_string_lossy().into_owned(), Some(compiler.sha256)))
+    }
+
+    /// Serves repeated compilations from `cache`.
+    pub fn with_compile_cache(mut self, cache: Arc<CompileCache>) -> Self {
+        self.compile_cache = Some(cache);
+        self
+    }
+
+    /// Addresses of libraries already deployed on this chain, keyed by fully qualified
+    /// name (`file:Name`) or by bare library name. They are linked instead of deployed.
+    pub fn with_libraries(mut self, libraries: BTreeMap<String, Address>) -> Self {
+        self.libraries = libraries;
+        self
+    }
+
+    /// Uses the CREATE2 factory at `factory` instead of [`DEFAULT_CREATE2_FACTORY`].
+    pub fn with_create2_factory(mut self, factory: Address) -> Self {
+        self.create2_factory = factory;
+        self
+    }
+
+    /// Prices transactions with a legacy gas price. Chains whose blocks carry no base fee
+    /// are priced that way regardless.
+    pub fn with_legacy_fees(mut self, legacy: bool) -> Self {
+        self.legacy_fees = legacy;
+        self
     }
 
-    /// Compiles a Solidity source string using solc CLI.
+    /// Rejects deployments estimated to cost more than `wei` in total.
+    pub fn with_max_deploy_cost(mut self, wei: U256) -> Self {
+        self.max_deploy_cost = Some(wei);
+        self
+    }
+
+    pub fn max_deploy_cost(&self) -> Option<U256> {
+        self.max_deploy_cost
+    }
+
+    /// Sets the confirmations awaited and when stuck transactions are replaced.
+    pub fn with_tracking(mut self, tracking: TrackingConfig) -> Self {
+        self.tracking = tracking;
+        self
+    }
+
+    /// Shares `nonces` with other engines, so that deployments through any of them from
+    /// the same account never reuse a nonce.
+    pub fn with_nonce_manager(mut self, nonces: Arc<NonceManager>) -> Self {
+        self.nonces = nonces;
+        self
+    }
+
+    /// Sets the wallet used to sign creation transactions.
+    pub fn with_deployer(mut self, wallet: LocalWallet) -> Self {
+        self.deployer = Some(wallet);
+        self
+    }
+
+    /// Compiles a Solidity source string, the source unit `source_name`, using solc's
+    /// standard JSON interface, and returns every contract it produces with
+    /// `main_contract` as the one to deploy.
+    /// Errors come back as [`DeployError::CompilationFailed`] with structured diagnostics;
+    /// warnings from a successful compilation are kept on the result.
+    /// With a compile cache set, identical compilations are served from it.
+    /// `settings` override the engine's defaults field by field. Imports are checked
+    /// against `resolver` before solc runs, which also supplies remappings and allowed paths.
     pub fn compile_solidity(
         &self,
         solidity_source: &str,
-        contract_name: &str,
-        base_path: &Path, // New parameter
-        remappings: &[String], // New parameter: e.g., "@openzeppelin/=lib/openzeppelin/"
-    ) -> Result<CompiledArtifact, DeployError> {
-        let mut temp_sol_file = NamedTempFile::new()?; // Handled by From<std::io::Error>
-        temp_sol_file.write_all(solidity_source.as_bytes())?;
-        let temp_sol_path = temp_sol_file.path();
-
-        // Output directory for ABI and BIN files - use a temporary directory
-        let temp_out_dir = Builder::new().prefix("solc_out_").tempdir()
-            .map_err(DeployError::TempDirError)?; // Specific error for tempdir
-        let out_dir_path_str = temp_out_dir.path().to_str().unwrap_or_default(); // Handle potential None from to_str
-
-        let mut cmd = Command::new(&self.solc_executable);
-        cmd.arg("--abi")
-           .arg("--bin")
-           .arg("--optimize")
-           .arg("--overwrite") // Important for subsequent calls
-           .arg("-o")
-           .arg(out_dir_path_str) // Output to temp directory
-           .arg("--base-path")    // Add base-path
-           .arg(base_path)        // The actual base path
-           .arg(temp_sol_path);   // Input .sol file
-
-        // Add remappings
-        for remap in remappings {
-            cmd.arg(remap);
-        }
-        
-        let output = cmd.output()?; // Handled by From<std::io::Error>
+        source_name: &str,
+        main_contract: &str,
+        resolver: &ImportResolver,
+        settings: &CompilerSettings,
+    ) -> Result<Compilation, DeployError> {
+        resolver.check_imports(solidity_source, source_name).map_err(DeployError::UnresolvedImports)?;
+        let base_path = resolver.base_path();
+        let settings = self.compiler_settings.overlay(settings);
+        let input = StandardJsonInput::new(source_name, solidity_source, &resolver.remappings(), &settings);
+        let input_json = serde_json::to_vec(&input)?;
+        let (solc_executable, solc_digest) = self.solc_for(solidity_source)?;
+
+        let cached = self.compile_cache.as_ref().and_then(|cache| {
+            let identity = solc_identity(&solc_executable, solc_digest)?;
+            let imported_files = resolver.imported_files(solidity_source, source_name);
+            Some((cache, cache_key(&input_json, main_contract, base_path, &identity, &imported_files)))
+        });
+        if let Some((cache, key)) = &cached {
+            if let Some(compilation) = cache.get(key) {
+                println!("Compile cache hit for {} ({})", main_contract, key);
+                return Ok(compilation);
+            }
+        }
+
+        let solc_output = self.run_solc(&solc_executable, &input_json, resolver)?;
+        let diagnostics = solc_output.diagnostics(source_name, solidity_source);
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(DeployError::CompilationFailed(diagnostics));
+        }
+        let compilation = Compilation::from_output(&solc_output, source_name, main_contract, diagnostics, &settings)?;
+        if let Some((cache, key)) = cached {
+            cache.put(&key, &compilation);
+        }
+        Ok(compilation)
+    }
+
+    /// Runs solc on `input_json` and parses its output.
+    fn run_solc(
+        &self,
+        solc_executable: &str,
+        input_json: &[u8],
+        resolver: &ImportResolver,
+    ) -> Result<StandardJsonOutput, DeployError> {
+        let mut command = Command::new(solc_executable);
+        command
+            .arg("--standard-json")
+            .arg("--base-path")    // Imports are resolved relative to the contracts dir
+            .arg(resolver.base_path());
+        let allow_paths = resolver.allow_paths();
+        if !allow_paths.is_empty() {
+            let joined: Vec<String> = allow_paths.iter().map(|p| p.display().to_string()).collect();
+            command.arg("--allow-paths").arg(joined.join(","));
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| DeployError::SolcError(format!("Could not run solc at '{}': {}", solc_executable, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input_json)?;
+        }
+        let output = child.wait_with_output()?;
 
+        // solc exits successfully even when the sources have errors; a non-zero status
+        // means the compiler itself could not run.
         if !output.status.success() {
             return Err(DeployError::SolcError(format!(
                 "solc failed with status: {}\nstdout: {}\nstderr: {}",
@@ -91,32 +771,697 @@ impl DeployEngine {
                 String::from_utf8_lossy(&output.stderr)
             )));
         }
+        serde_json::from_slice(&output.stdout).map_err(|e| {
+            DeployError::SolcError(format!(
+                "Could not parse solc output: {}\nstderr: {}",
+                e,
+                String::from_utf8_lossy(&output.stderr)
+            ))
+        })
+    }
 
-        // Construct paths to ABI and BIN files within the temp output directory
-        let abi_file_path = temp_out_dir.path().join(format!("{}.abi", contract_name));
-        let bin_file_path = temp_out_dir.path().join(format!("{}.bin", contract_name));
+    fn provider(&self) -> Result<Provider<Http>, DeployError> {
+        Provider::<Http>::try_from(self.rpc_url.as_str())
+            .map_err(|e| DeployError::EthersError(format!("Invalid RPC URL {}: {}", self.rpc_url, e)))
+    }
 
-        let abi_str = fs::read_to_string(&abi_file_path)
-            .map_err(|e| DeployError::NoAbiFound(format!("Could not read ABI file {:?}: {}", abi_file_path, e)))?;
-        let bytecode_hex = fs::read_to_string(&bin_file_path)
-            .map_err(|e| DeployError::NoBytecodeFound(format!("Could not read BIN file {:?}: {}", bin_file_path, e)))?;
-         
-        Ok(CompiledArtifact {
-            contract_name: contract_name.to_string(),
-            abi: abi_str,
-            bytecode: bytecode_hex.trim().to_string(),
+    /// Deploys the main contract of `compilation`, first linking every library it needs.
+    ///
+    /// A library's address comes from the engine's configured libraries, then from
+    /// `find_stored`, which looks up earlier deployments of the same bytecode; libraries
+    /// with neither are deployed first. Every library appears as a step in the result.
+    /// With a `salt`, the main contract is deployed through the CREATE2 factory, at the
+    /// address [`Self::predict_address`] reports.
+    pub async fn deploy_linked(
+        &self,
+        compilation: &Compilation,
+        constructor_params: &Value,
+        options: &DeployOptions,
+        find_stored: impl Fn(&CompiledArtifact) -> Option<Address>,
+    ) -> Result<DeploymentResult, DeployError> {
+        let plan = self.link_plan(compilation, &find_stored)?;
+        for (library, known) in &plan {
+            // A library that is not on this chain would make every call into it revert.
+            if let Some((address, _)) = known {
+                let code = self.provider()?.get_code(*address, None).await
+                    .map_err(|e| DeployError::EthersError(e.to_string()))?;
+                if code.is_empty() {
+                    return Err(DeployError::LinkError(format!("no code at {:?}, the address of {}", address, library.qualified_name())));
+                }
+            }
+        }
+
+        // Checked before anything is sent, so a rejected deployment leaves no libraries behind.
+        let estimate = self.estimate_plan(&plan, compilation, constructor_params, options).await?;
+        if let Some(max) = self.max_deploy_cost {
+            if estimate.total_cost_wei > max {
+                return Err(DeployError::CostLimitExceeded(format!(
+                    "estimated at up to {} ETH, above the maximum of {} ETH",
+                    estimate.total_cost_eth,
+                    ethers::utils::format_ether(max)
+                )));
+            }
+        }
+        let fees = estimate.fees;
+
+        let mut addresses = BTreeMap::new();
+        let mut steps = Vec::new();
+        for (library, known) in plan {
+            let name = library.qualified_name();
+            let step = match known {
+                Some((address, source)) => {
+                    println!("Linking {} at {:?} ({:?})", name, address, source);
+                    LinkStep { library: name, address, source, tx_hash: None, block_number: None, gas_used: None }
+                }
+                None => {
+                    let linked = CompiledArtifact {
+                        bytecode: link_bytecode(&library.bytecode, &library.link_references, &addresses)?,
+                        ..library.clone()
+                    };
+                    let deployment = self.deploy_contract(&linked, &Value::Null, &fees, options.events.as_ref()).await?;
+                    println!("Deployed library {} at {:?}", name, deployment.address);
+                    LinkStep {
+                        library: name,
+                        address: deployment.address,
+                        source: LibrarySource::Deployed,
+                        tx_hash: Some(deployment.tx_hash),
+                        block_number: Some(deployment.block_number),
+                        gas_used: Some(deployment.gas_used),
+                    }
+                }
+            };
+            addresses.insert(step.library.clone(), step.address);
+            emit(options.events.as_ref(), DeployEvent::Linked { step: step.clone() });
+            steps.push(step);
+        }
+
+        let main = &compilation.main;
+        let linked = CompiledArtifact {
+            bytecode: link_bytecode(&main.bytecode, &main.link_references, &addresses)?,
+            ..main.clone()
+        };
+        let events = options.events.as_ref();
+        let mut deployment = match options.salt {
+            Some(salt) => self.deploy_create2(&linked, constructor_params, salt, &fees, events).await?,
+            None => self.deploy_contract(&linked, constructor_params, &fees, events).await?,
+        };
+        deployment.libraries = steps;
+        Ok(deployment)
+    }
+
+    /// Estimates the gas and cost of [`Self::deploy_linked`] with the same arguments,
+    /// without sending anything.
+    pub async fn estimate_deployment(
+        &self,
+        compilation: &Compilation,
+        constructor_params: &Value,
+        options: &DeployOptions,
+        find_stored: impl Fn(&CompiledArtifact) -> Option<Address>,
+    ) -> Result<GasEstimate, DeployError> {
+        let plan = self.link_plan(compilation, &find_stored)?;
+        self.estimate_plan(&plan, compilation, constructor_params, options).await
+    }
+
+    async fn estimate_plan(
+        &self,
+        plan: &LinkPlan<'_>,
+        compilation: &Compilation,
+        constructor_params: &Value,
+        options: &DeployOptions,
+    ) -> Result<GasEstimate, DeployError> {
+        let fees = self.fee_params(options.fee).await?;
+        let mut transactions = Vec::new();
+        let mut addresses = BTreeMap::new();
+        for (library, known) in plan {
+            let name = library.qualified_name();
+            let address = match known {
+                Some((address, _)) => *address,
+                None => {
+                    let linked = CompiledArtifact {
+                        bytecode: link_bytecode(&library.bytecode, &library.link_references, &addresses)?,
+                        ..(*library).clone()
+                    };
+                    let gas_limit = self.estimate_gas(None, build_creation_code(&linked, &Value::Null)?, &fees).await?;
+                    transactions.push(TxEstimate { contract: name.clone(), gas_limit });
+                    // Not deployed yet. Creation gas barely depends on the linked address.
+                    Address::zero()
+                }
+            };
+            addresses.insert(name, address);
+        }
+
+        let main = &compilation.main;
+        let linked = CompiledArtifact {
+            bytecode: link_bytecode(&main.bytecode, &main.link_references, &addresses)?,
+            ..main.clone()
+        };
+        let init_code = build_creation_code(&linked, constructor_params)?;
+        let gas_limit = match options.salt {
+            Some(salt) => {
+                let mut calldata = salt.as_bytes().to_vec();
+                calldata.extend(init_code);
+                self.estimate_gas(Some(self.create2_factory), calldata, &fees).await?
+            }
+            None => self.estimate_gas(None, init_code, &fees).await?,
+        };
+        transactions.push(TxEstimate { contract: main.qualified_name(), gas_limit });
+
+        let gas_limit = transactions.iter().map(|tx| tx.gas_limit).sum::<u64>();
+        let total_cost_wei = fees.max_price_per_gas() * gas_limit;
+        Ok(GasEstimate {
+            gas_limit,
+            fees,
+            total_cost_wei,
+            total_cost_eth: ethers::utils::format_ether(total_cost_wei),
+            transactions,
         })
     }
-    
-    /// Placeholder for deploying a compiled contract.
+
+    /// Fees for `strategy` at the chain's current prices. EIP-1559 chains pay a priority
+    /// fee at the strategy's percentile of the last 10 blocks, and at most twice the next
+    /// base fee on top of it.
+    pub async fn fee_params(&self, strategy: FeeStrategy) -> Result<FeeParams, DeployError> {
+        let provider = self.provider()?;
+        let rpc_error = |e: ProviderError| DeployError::EthersError(e.to_string());
+        let (percentile, legacy_percent) = strategy.percentiles();
+
+        let latest = provider.get_block(BlockNumber::Latest).await.map_err(rpc_error)?;
+        let base_fee = latest.and_then(|block| block.base_fee_per_gas);
+        if self.legacy_fees || base_fee.is_none() {
+            let gas_price = match strategy {
+                FeeStrategy::Fixed { max_fee_per_gas, .. } => max_fee_per_gas,
+                _ => provider.get_gas_price().await.map_err(rpc_error)? * legacy_percent / 100,
+            };
+            return Ok(FeeParams::Legacy { gas_price });
+        }
+
+        let history = provider.fee_history(10u64, BlockNumber::Latest, &[percentile]).await.map_err(rpc_error)?;
+        let base_fee_per_gas = history.base_fee_per_gas.last().copied().or(base_fee).unwrap_or_default();
+        let (max_fee_per_gas, max_priority_fee_per_gas) = match strategy {
+            FeeStrategy::Fixed { max_fee_per_gas, max_priority_fee_per_gas } => (max_fee_per_gas, max_priority_fee_per_gas),
+            _ => {
+                let mut rewards: Vec<U256> = history.reward.iter().filter_map(|block| block.first().copied()).collect();
+                rewards.sort();
+                let priority = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+                (base_fee_per_gas * 2 + priority, priority)
+            }
+        };
+        Ok(FeeParams::Eip1559 { base_fee_per_gas, max_fee_per_gas, max_priority_fee_per_gas })
+    }
+
+    /// Gas needed by a transaction from the deployer to `to`, or a contract creation.
+    async fn estimate_gas(&self, to: Option<Address>, data: Vec<u8>, fees: &FeeParams) -> Result<u64, DeployError> {
+        let mut tx = priced_transaction(to, data, fees);
+        if let Some(wallet) = &self.deployer {
+            tx.set_from(wallet.address());
+        }
+        let gas = self.provider()?.estimate_gas(&tx, None).await
+            .map_err(|e| DeployError::EthersError(format!("gas estimation failed: {}", e)))?;
+        Ok(gas.as_u64())
+    }
+
+    /// The address a CREATE2 deployment of `compilation` with `salt` would get, computed
+    /// without sending anything. Every library must already have an address, since one
+    /// deployed later would change the linked bytecode.
+    pub fn predict_address(
+        &self,
+        compilation: &Compilation,
+        constructor_params: &Value,
+        salt: H256,
+        find_stored: impl Fn(&CompiledArtifact) -> Option<Address>,
+    ) -> Result<AddressPrediction, DeployError> {
+        let mut addresses = BTreeMap::new();
+        let mut libraries = Vec::new();
+        for (library, known) in self.link_plan(compilation, &find_stored)? {
+            let name = library.qualified_name();
+            let (address, source) = known.ok_or_else(|| DeployError::LinkError(format!(
+                "{} has no known address on this chain; deploy it before predicting addresses that link against it",
+                name
+            )))?;
+            addresses.insert(name.clone(), address);
+            libraries.push(LinkStep { library: name, address, source, tx_hash: None, block_number: None, gas_used: None });
+        }
+
+        let main = &compilation.main;
+        let linked = CompiledArtifact {
+            bytecode: link_bytecode(&main.bytecode, &main.link_references, &addresses)?,
+            ..main.clone()
+        };
+        let init_code = build_creation_code(&linked, constructor_params)?;
+        let create2 = self.create2_info(&init_code, salt);
+        let address = ethers::utils::get_create2_address_from_hash(create2.factory, salt, create2.init_code_hash);
+        Ok(AddressPrediction { address, create2, libraries })
+    }
+
+    fn create2_info(&self, init_code: &[u8], salt: H256) -> Create2Info {
+        Create2Info { factory: self.create2_factory, salt, init_code_hash: H256::from(ethers::utils::keccak256(init_code)) }
+    }
+
+    /// The libraries the main contract needs, dependencies first, each with its address
+    /// if one is already known. Libraries only needed by a known library are skipped.
+    fn link_plan<'a>(
+        &self,
+        compilation: &'a Compilation,
+        find_stored: &impl Fn(&CompiledArtifact) -> Option<Address>,
+    ) -> Result<LinkPlan<'a>, DeployError> {
+        fn visit<'a>(
+            engine: &DeployEngine,
+            artifact: &'a CompiledArtifact,
+            compilation: &'a Compilation,
+            find_stored: &impl Fn(&CompiledArtifact) -> Option<Address>,
+            path: &mut Vec<String>,
+            plan: &mut LinkPlan<'a>,
+        ) -> Result<(), DeployError> {
+            for (file, libraries) in &artifact.link_references {
+                for name in libraries.keys() {
+                    let qualified = format!("{}:{}", file, name);
+                    if plan.iter().any(|(planned, _)| planned.qualified_name() == qualified) {
+                        continue;
+                    }
+                    if path.contains(&qualified) {
+                        return Err(DeployError::LinkError(format!("libraries {} link against each other", path.join(", "))));
+                    }
+                    let library = compilation.libraries.iter()
+                        .find(|library| library.source_name == *file && library.contract_name == *name)
+                        .ok_or_else(|| DeployError::LinkError(format!("{} is not part of the compilation", qualified)))?;
+
+                    let configured = engine.libraries.get(&qualified).or_else(|| engine.libraries.get(name));
+                    let known = configured.map(|address| (*address, LibrarySource::Configured))
+                        .or_else(|| find_stored(library).map(|address| (address, LibrarySource::Stored)));
+                    if known.is_none() {
+                        path.push(qualified);
+                        visit(engine, library, compilation, find_stored, path, plan)?;
+                        path.pop();
+                    }
+                    plan.push((library, known));
+                }
+            }
+            Ok(())
+        }
+
+        let mut plan = Vec::new();
+        visit(self, &compilation.main, compilation, find_stored, &mut Vec::new(), &mut plan)?;
+        Ok(plan)
+    }
+
+    /// Sends the creation transaction for `artifact` to the configured RPC endpoint
+    /// and waits for it to be mined. `constructor_params` are ABI-encoded against the
+    /// artifact's constructor, see [`build_creation_code`]. The bytecode must already be
+    /// linked, see [`Self::deploy_linked`]. Progress is reported to `events`, if given.
     pub async fn deploy_contract(
         &self,
-        _abi: String, // Changed from Value to String
-        _bytecode: String,
-        _constructor_args: Option<Value>,
-    ) -> Result<String, DeployError> {
-        println!("Simulating contract deployment...");
-        Ok("0xSIMULATED_DEPLOYED_ADDRESS".to_string())
+        artifact: &CompiledArtifact,
+        constructor_params: &Value,
+        fees: &FeeParams,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<DeploymentResult, DeployError> {
+        let bytecode = build_creation_code(artifact, constructor_params)?;
+        let tx = priced_transaction(None, bytecode, fees);
+        let receipt = self.send_transaction(tx, &artifact.qualified_name(), None, events).await?;
+        let address = receipt.contract_address
+            .ok_or_else(|| DeployError::NoReceipt(format!("Receipt for {:?} has no contract address", receipt.transaction_hash)))?;
+
+        Ok(DeploymentResult {
+            address,
+            deployer: receipt.from,
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+            libraries: Vec::new(),
+            create2: None,
+        })
+    }
+
+    /// Deploys `artifact` through the CREATE2 factory with `salt`. Fails without sending
+    /// anything if the chain has no factory or the target address already holds code.
+    async fn deploy_create2(
+        &self,
+        artifact: &CompiledArtifact,
+        constructor_params: &Value,
+        salt: H256,
+        fees: &FeeParams,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<DeploymentResult, DeployError> {
+        let init_code = build_creation_code(artifact, constructor_params)?;
+        let create2 = self.create2_info(&init_code, salt);
+        let address = ethers::utils::get_create2_address_from_hash(create2.factory, salt, create2.init_code_hash);
+
+        let provider = self.provider()?;
+        let code_at = |address: Address| {
+            let provider = provider.clone();
+            async move {
+                provider.get_code(address, None).await.map_err(|e| DeployError::EthersError(e.to_string()))
+            }
+        };
+        if code_at(create2.factory).await?.is_empty() {
+            return Err(DeployError::Create2Error(format!("no CREATE2 factory at {:?} on this chain", create2.factory)));
+        }
+        if !code_at(address).await?.is_empty() {
+            return Err(DeployError::Create2Error(format!(
+                "{:?} already holds a contract; deploy with another salt", address
+            )));
+        }
+
+        let mut calldata = salt.as_bytes().to_vec();
+        calldata.extend(init_code);
+        let tx = priced_transaction(Some(create2.factory), calldata, fees);
+        let receipt = self.send_transaction(tx, &artifact.qualified_name(), Some(address), events).await?;
+        if code_at(address).await?.is_empty() {
+            return Err(DeployError::NoReceipt(format!(
+                "Transaction {:?} left no code at {:?}", receipt.transaction_hash, address
+            )));
+        }
+
+        Ok(DeploymentResult {
+            address,
+            deployer: receipt.from,
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+            libraries: Vec::new(),
+            create2: Some(create2),
+        })
+    }
+
+    /// Signs `tx` from the deployer, sends it and waits for a successful receipt. The
+    /// contract is expected at `create2_address`, or else at the usual CREATE address of
+    /// the deployer and nonce. The transaction hash is reported once the node accepts it.
+    async fn send_transaction(
+        &self,
+        mut tx: TypedTransaction,
+        label: &str,
+        create2_address: Option<Address>,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<TransactionReceipt, DeployError> {
+        let wallet = self.deployer.clone()
+            .ok_or_else(|| DeployError::SignerError("No deployer key configured".to_string()))?;
+        let rpc_error = |e: ethers::middleware::signer::SignerMiddlewareError<Provider<Http>, LocalWallet>| DeployError::EthersError(e.to_string());
+
+        let provider = self.provider()?;
+        let chain_id = provider.get_chainid().await
+            .map_err(|e| DeployError::EthersError(e.to_string()))?
+            .as_u64();
+        let client = SignerMiddleware::new(provider.clone(), wallet.with_chain_id(chain_id));
+        let from = client.address();
+        let pending_count = || async {
+            provider.get_transaction_count(from, Some(BlockNumber::Pending.into())).await
+                .map_err(|e| DeployError::EthersError(e.to_string()))
+        };
+
+        emit(events, DeployEvent::Signing { contract: label.to_string() });
+        let mut nonce = self.nonces.reserve(chain_id, from, pending_count()).await?;
+        tx.set_nonce(nonce);
+        if let Err(e) = client.fill_transaction(&mut tx, None).await {
+            self.nonces.release(chain_id, from, nonce).await;
+            return Err(rpc_error(e));
+        }
+
+        // A nonce the node rejects was taken by a transaction this manager does not know
+        // of, or freed by a dropped one: resync from the node and sign again.
+        let mut attempt = 1;
+        let (tx_hash, raw) = loop {
+            let signature = match client.signer().sign_transaction(&tx).await {
+                Ok(signature) => signature,
+                Err(e) => {
+                    self.nonces.release(chain_id, from, nonce).await;
+                    return Err(DeployError::SignerError(e.to_string()));
+                }
+            };
+            let raw = tx.rlp_signed(&signature);
+            let tx_hash = H256::from(ethers::utils::keccak256(&raw));
+            let sent = client.send_raw_transaction(raw.clone()).await;
+            self.nonces.settle(chain_id, from, nonce).await;
+            match sent {
+                Ok(_) => break (tx_hash, raw),
+                // The node already holds this very transaction: it was sent, keep tracking it.
+                Err(e) if is_already_known(&e.to_string()) => break (tx_hash, raw),
+                Err(e) if attempt < NONCE_ATTEMPTS && is_nonce_error(&e.to_string()) => {
+                    eprintln!("Nonce {} for {} was rejected ({}); resyncing", nonce, label, e);
+                    self.nonces.resync(chain_id, from).await;
+                    nonce = self.nonces.reserve(chain_id, from, pending_count()).await?;
+                    tx.set_nonce(nonce);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.nonces.resync(chain_id, from).await;
+                    return Err(rpc_error(e));
+                }
+            }
+        };
+        println!("Creation transaction for {} sent with nonce {}: {:?}", label, nonce, tx_hash);
+        let address = create2_address.unwrap_or_else(|| ethers::utils::get_contract_address(from, nonce));
+        emit(events, DeployEvent::Broadcasting { contract: label.to_string(), tx_hash, nonce, address });
+        emit(events, DeployEvent::Confirming { contract: label.to_string(), tx_hash });
+
+        let tracked = Tracked {
+            label: label.to_string(),
+            from,
+            nonce,
+            hashes: vec![tx_hash],
+            resend: Some(Resendable { wallet: client.signer().clone(), tx, raw }),
+        };
+        let receipt = self.track(tracked, events).await;
+        if receipt.is_err() {
+            // The nonce may be used or free; only the node can tell.
+            self.nonces.resync(chain_id, from).await;
+        }
+        receipt?.ok_or_else(|| DeployError::NoReceipt(format!("Transaction {:?} was dropped", tx_hash)))
+    }
+
+    /// Follows transactions sent earlier, e.g. before a restart, until they are final.
+    /// `hashes` are every version sent from the deployer with `nonce`, the latest last.
+    /// Returns `None` if the node knows none of them, i.e. they were dropped or never
+    /// arrived; they cannot be sent again from here.
+    pub async fn confirm_sent(
+        &self,
+        label: &str,
+        nonce: U256,
+        hashes: Vec<TxHash>,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<Option<TransactionReceipt>, DeployError> {
+        let from = self.deployer.as_ref()
+            .ok_or_else(|| DeployError::SignerError("No deployer key configured".to_string()))?
+            .address();
+        self.track(Tracked { label: label.to_string(), from, nonce, hashes, resend: None }, events).await
+    }
+
+    /// Polls for `tracked` until one of its versions is mined with the configured number
+    /// of confirmations, and returns that receipt.
+    ///
+    /// A receipt that disappears, or moves to another block, is a reorg: the transaction
+    /// goes back to pending and its confirmations are counted again. A pending transaction
+    /// the node no longer knows is sent again, and one pending past the stuck timeout is
+    /// replaced with higher fees. If the nonce gets used by a transaction that is none of
+    /// ours, the deployment fails.
+    async fn track(
+        &self,
+        mut tracked: Tracked,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<Option<TransactionReceipt>, DeployError> {
+        let provider = self.provider()?;
+        let rpc_error = |e: ProviderError| DeployError::EthersError(e.to_string());
+        let config = self.tracking;
+        let required = config.confirmations.max(1);
+        let mut pending_since = Instant::now();
+        let mut replacements = 0;
+        let mut mined: Option<TransactionReceipt> = None;
+        let mut reported = 0;
+
+        loop {
+            match (tracked.receipt(&provider).await?, mined.take()) {
+                (Some(receipt), previous) => {
+                    let block_number = receipt.block_number.unwrap_or_default().as_u64();
+                    if let Some(previous) = previous.filter(|previous| previous.block_hash != receipt.block_hash) {
+                        tracked.reorged(&previous, events);
+                        reported = 0;
+                    }
+                    let latest = provider.get_block_number().await.map_err(rpc_error)?.as_u64();
+                    let confirmations = (latest + 1).saturating_sub(block_number);
+                    if confirmations != reported {
+                        reported = confirmations;
+                        emit(events, DeployEvent::Confirmation {
+                            contract: tracked.label.clone(),
+                            tx_hash: receipt.transaction_hash,
+                            block_number,
+                            confirmations,
+                            required,
+                        });
+                    }
+                    if confirmations >= required {
+                        if receipt.status != Some(U64::from(1)) {
+                            return Err(DeployError::NoReceipt(format!("Transaction {:?} reverted", receipt.transaction_hash)));
+                        }
+                        emit(events, DeployEvent::Confirmed {
+                            contract: tracked.label.clone(),
+                            tx_hash: receipt.transaction_hash,
+                            block_number,
+                        });
+                        return Ok(Some(receipt));
+                    }
+                    mined = Some(receipt);
+                }
+                (None, Some(previous)) => {
+                    tracked.reorged(&previous, events);
+                    reported = 0;
+                    pending_since = Instant::now();
+                    continue;
+                }
+                (None, None) => {
+                    let next_nonce = provider.get_transaction_count(tracked.from, Some(BlockNumber::Latest.into())).await
+                        .map_err(rpc_error)?;
+                    if next_nonce > tracked.nonce {
+                        // Mined since the receipt lookup, or taken by someone else's transaction.
+                        if tracked.receipt(&provider).await?.is_some() {
+                            continue;
+                        }
+                        if tracked.resend.is_none() && !tracked.known(&provider).await? {
+                            return Ok(None);
+                        }
+                        return Err(DeployError::NoReceipt(format!(
+                            "Nonce {} of {:?} was used by another transaction; {} was not deployed",
+                            tracked.nonce, tracked.from, tracked.label
+                        )));
+                    }
+                    if !tracked.known(&provider).await? {
+                        let Some(resend) = &tracked.resend else {
+                            return Ok(None);
+                        };
+                        println!("Transaction {:?} for {} was dropped; sending it again", tracked.latest(), tracked.label);
+                        if let Err(e) = provider.send_raw_transaction(resend.raw.clone()).await {
+                            eprintln!("Could not send {:?} again: {}", tracked.latest(), e);
+                        }
+                    } else if config.stuck_timeout.is_some_and(|timeout| pending_since.elapsed() >= timeout)
+                        && tracked.resend.is_some()
+                        && replacements < config.max_replacements
+                    {
+                        tracked.replace(&provider, config.fee_bump_percent, events).await?;
+                        replacements += 1;
+                        pending_since = Instant::now();
+                    }
+                }
+            }
+            tokio::time::sleep(config.poll_interval).await;
+        }
+    }
+}
+
+/// A transaction followed by [`DeployEngine::track`]: every version sent with one nonce.
+struct Tracked {
+    label: String, // Fully qualified name of the contract it creates
+    from: Address,
+    nonce: U256,
+    hashes: Vec<TxHash>, // Every version sent, the latest last
+    resend: Option<Resendable>, // Unset for transactions sent before a restart
+}
+
+/// The latest signed version of a tracked transaction, to send again or replace.
+struct Resendable {
+    wallet: LocalWallet,
+    tx: TypedTransaction,
+    raw: Bytes,
+}
+
+impl Tracked {
+    fn latest(&self) -> TxHash {
+        self.hashes.last().copied().unwrap_or_default()
+    }
+
+    /// The receipt of whichever version was mined, if any.
+    async fn receipt(&self, provider: &Provider<Http>) -> Result<Option<TransactionReceipt>, DeployError> {
+        for tx_hash in self.hashes.iter().rev() {
+            let receipt = provider.get_transaction_receipt(*tx_hash).await
+                .map_err(|e| DeployError::EthersError(e.to_string()))?;
+            if receipt.is_some() {
+                return Ok(receipt);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the node has any version, mined or pending.
+    async fn known(&self, provider: &Provider<Http>) -> Result<bool, DeployError> {
+        for tx_hash in self.hashes.iter().rev() {
+            let tx = provider.get_transaction(*tx_hash).await
+                .map_err(|e| DeployError::EthersError(e.to_string()))?;
+            if tx.is_some() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn reorged(&self, receipt: &TransactionReceipt, events: Option<&UnboundedSender<DeployEvent>>) {
+        let block_number = receipt.block_number.unwrap_or_default().as_u64();
+        println!("Block {} holding {:?} for {} was reorged out", block_number, receipt.transaction_hash, self.label);
+        emit(events, DeployEvent::Reorged { contract: self.label.clone(), tx_hash: receipt.transaction_hash, block_number });
+    }
+
+    /// Signs the latest version again with its fees raised by `percent` and sends it.
+    /// If the node rejects it, e.g. because the original was mined meanwhile, the
+    /// versions already sent are tracked as before.
+    async fn replace(
+        &mut self,
+        provider: &Provider<Http>,
+        percent: u64,
+        events: Option<&UnboundedSender<DeployEvent>>,
+    ) -> Result<(), DeployError> {
+        let replaced = self.latest();
+        let Some(resend) = &mut self.resend else {
+            return Ok(());
+        };
+        let mut tx = resend.tx.clone();
+        bump_fees(&mut tx, percent);
+        let signature = resend.wallet.sign_transaction(&tx).await
+            .map_err(|e| DeployError::SignerError(e.to_string()))?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = H256::from(ethers::utils::keccak256(&raw));
+        println!("Transaction {:?} for {} is stuck; replacing it with {:?}", replaced, self.label, tx_hash);
+        if let Err(e) = provider.send_raw_transaction(raw.clone()).await {
+            eprintln!("Could not replace {:?} with {:?}: {}", replaced, tx_hash, e);
+            return Ok(());
+        }
+        resend.tx = tx;
+        resend.raw = raw;
+        self.hashes.push(tx_hash);
+        emit(events, DeployEvent::Replaced { contract: self.label.clone(), replaced, tx_hash, nonce: self.nonce });
+        Ok(())
+    }
+}
+
+/// Times a transaction is signed with a fresh nonce after the node rejects its nonce.
+const NONCE_ATTEMPTS: u32 = 3;
+
+/// Whether a node rejected a sent transaction because its nonce is taken, so that
+/// signing it again with a fresh nonce cannot deploy the contract twice.
+fn is_nonce_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["nonce too low", "replacement transaction underpriced"].iter().any(|needle| message.contains(needle))
+}
+
+/// Whether a node's error for a sent transaction means it already holds that exact
+/// transaction in its pool.
+fn is_already_known(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("already known") || message.contains("already imported")
+}
+
+/// Raises every fee of `tx` by `percent`, and by at least one wei.
+fn bump_fees(tx: &mut TypedTransaction, percent: u64) {
+    let bump = |fee: Option<U256>| fee.map(|fee| fee * (100 + percent) / 100 + 1);
+    match tx {
+        TypedTransaction::Eip1559(tx) => {
+            tx.max_fee_per_gas = bump(tx.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = bump(tx.max_priority_fee_per_gas);
+        }
+        TypedTransaction::Legacy(tx) => tx.gas_price = bump(tx.gas_price),
+        TypedTransaction::Eip2930(tx) => tx.tx.gas_price = bump(tx.tx.gas_price),
+    }
+}
+
+fn emit(events: Option<&UnboundedSender<DeployEvent>>, event: DeployEvent) {
+    if let Some(events) = events {
+        // The receiver only listens for progress; a closed channel is not an error.
+        let _ = events.send(event);
     }
 }
 
@@ -124,7 +1469,8 @@ impl DeployEngine {
 /*
 async fn example_deploy() {
     let solc_exe = env::var("SOLC_PATH").unwrap_or_else(|_| "solc".to_string());
-    let engine = DeployEngine::new(solc_exe); 
+    let engine = DeployEngine::new(solc_exe, "http://127.0.0.1:8545".to_string())
+        .with_deployer("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".parse().unwrap());
     let source_code = r#"
 // SPDX-License-Identifier: MIT
 pragma solidity ^0.8.0;
@@ -150,8 +1496,8 @@ contract MyContract is Context {
             println!("ABI: {}", comp_output.abi); // abi is now a String
             println!("Bytecode: {}", comp_output.bytecode);
             
-            match engine.deploy_contract(comp_output.abi.clone(), comp_output.bytecode.clone(), None).await { // Clone if needed, or adjust ownership
-                Ok(address) => println!("Deployed to: {}", address),
+            match engine.deploy_contract(&comp_output, &serde_json::json!([42])).await {
+                Ok(deployment) => println!("Deployed to: {:?}", deployment.address),
                 Err(e) => eprintln!("Deployment error: {:?}", e),
             }
         }
@@ -159,3 +1505,1128 @@ contract MyContract is Context {
     }
 }
 */
+
+/// What identifies the compiler in a compile cache key: the binary's SHA-256 when it is
+/// known, otherwise its `--version` output. `None` if the version cannot be determined,
+/// in which case the compilation is not cached.
+fn solc_identity(solc_executable: &str, digest: Option<String>) -> Option<String> {
+    if let Some(digest) = digest {
+        return Some(format!("sha256:{}", digest));
+    }
+    let output = Command::new(solc_executable).arg("--version").output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A transaction to `to`, or a contract creation, carrying `data` and priced with `fees`.
+/// Nonce and gas limit are left for the signer to fill in.
+fn priced_transaction(to: Option<Address>, data: Vec<u8>, fees: &FeeParams) -> TypedTransaction {
+    let mut tx: TypedTransaction = match fees {
+        FeeParams::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas, .. } => Eip1559TransactionRequest::new()
+            .max_fee_per_gas(*max_fee_per_gas)
+            .max_priority_fee_per_gas(*max_priority_fee_per_gas)
+            .into(),
+        FeeParams::Legacy { gas_price } => TransactionRequest::new().gas_price(*gas_price).into(),
+    };
+    tx.set_data(data.into());
+    if let Some(to) = to {
+        tx.set_to(to);
+    }
+    tx
+}
+
+/// Returns the creation bytecode of `artifact` with its ABI-encoded constructor
+/// arguments appended.
+///
+/// Each constructor input is looked up in `params` by name, falling back to the name
+/// without its leading underscore, so the template's `token_address` feeds the
+/// constructor's `_token_address`. A JSON array in `params` is matched positionally.
+pub fn build_creation_code(artifact: &CompiledArtifact, params: &Value) -> Result<Vec<u8>, DeployError> {
+    if artifact.bytecode.contains("__$") {
+        return Err(DeployError::LinkError(format!("the bytecode of {} has unlinked library placeholders", artifact.contract_name)));
+    }
+    let mut bytecode = hex::decode(artifact.bytecode.trim().trim_start_matches("0x"))
+        .map_err(|e| DeployError::InvalidBytecode(format!("{}: {}", artifact.contract_name, e)))?;
+    if bytecode.is_empty() {
+        return Err(DeployError::InvalidBytecode(format!(
+            "{} has no creation bytecode (abstract contract or interface?)",
+            artifact.contract_name
+        )));
+    }
+    bytecode.extend(encode_constructor_args(&artifact.raw_abi, params)?);
+    Ok(bytecode)
+}
+
+/// Replaces the library placeholders of `bytecode` at `link_references` with the
+/// addresses in `addresses`, keyed by fully qualified library name.
+pub fn link_bytecode(
+    bytecode: &str,
+    link_references: &LinkReferences,
+    addresses: &BTreeMap<String, Address>,
+) -> Result<String, DeployError> {
+    let mut linked = bytecode.to_string();
+    for (file, libraries) in link_references {
+        for (name, ranges) in libraries {
+            let qualified = format!("{}:{}", file, name);
+            let address = addresses.get(&qualified)
+                .ok_or_else(|| DeployError::LinkError(format!("no address for {}", qualified)))?;
+            let address_hex = hex::encode(address.as_bytes());
+            for range in ranges {
+                // Offsets are in bytes, two hex digits each.
+                let (start, end) = (range.start * 2, (range.start + range.length) * 2);
+                if range.length != 20 || linked.get(start..end).is_none() {
+                    return Err(DeployError::LinkError(format!(
+                        "invalid link reference for {} at byte {}", qualified, range.start
+                    )));
+                }
+                linked.replace_range(start..end, &address_hex);
+            }
+        }
+    }
+    Ok(linked)
+}
+
+#[derive(Deserialize)]
+struct AbiEntryNames {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    inputs: Vec<AbiParamNames>,
+}
+
+/// ABI-encodes `params` against the constructor declared in `abi_json`, an ABI as
+/// emitted by solc. Returns an empty vector when the contract has no constructor inputs.
+pub fn encode_constructor_args(abi_json: &Value, params: &Value) -> Result<Vec<u8>, DeployError> {
+    let abi: Abi = serde_json::from_value(abi_json.clone())?;
+    let constructor = match abi.constructor() {
+        Some(constructor) if !constructor.inputs.is_empty() => constructor,
+        _ => return Ok(Vec::new()),
+    };
+    let entries: Vec<AbiEntryNames> = serde_json::from_value(abi_json.clone())?;
+    let names = entries.into_iter()
+        .find(|entry| entry.kind == "constructor")
+        .map(|entry| entry.inputs)
+        .unwrap_or_default();
+
+    let mut tokens = Vec::with_capacity(constructor.inputs.len());
+    for (index, input) in constructor.inputs.iter().enumerate() {
+        let param_name = if input.name.is_empty() { format!("#{}", index) } else { input.name.clone() };
+        let value = match params {
+            Value::Array(values) => values.get(index),
+            Value::Object(map) => map.get(&input.name)
+                .or_else(|| map.get(input.name.trim_start_matches('_'))),
+            _ => None,
+        };
+        let value = value.ok_or_else(|| DeployError::ConstructorArgError {
+            param: param_name.clone(),
+            reason: format!("missing value for constructor input of type {}", input.kind),
+        })?;
+        let component_names = names.get(index).map(|n| n.components.as_slice()).unwrap_or_default();
+        tokens.push(json_to_token(value, &input.kind, component_names, &param_name)?);
+    }
+    Ok(ethers::abi::encode(&tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{ParamType, Token};
+    use crate::solc_standard_json::{ContractKind, EvmVersion};
+    use ethers::utils::Anvil;
+    use std::collections::{HashMap, VecDeque};
+    use std::path::Path;
+
+    // Init code that deploys a runtime returning the constant 42.
+    const RETURN_42_INIT_CODE: &str = "600a600c600039600a6000f3602a60005260206000f3";
+
+    const ONE_WEI: FeeParams = FeeParams::Legacy { gas_price: U256([1, 0, 0, 0]) };
+
+    fn artifact(bytecode: &str) -> CompiledArtifact {
+        CompiledArtifact {
+            contract_name: "Return42".to_string(),
+            raw_abi: serde_json::json!([]),
+            bytecode: bytecode.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deploy_without_deployer_fails() {
+        let engine = DeployEngine::new("solc".to_string(), "http://127.0.0.1:1".to_string());
+        let result = engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &ONE_WEI, None).await;
+        assert!(matches!(result, Err(DeployError::SignerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deploy_rejects_empty_bytecode() {
+        let engine = DeployEngine::new("solc".to_string(), "http://127.0.0.1:1".to_string())
+            .with_deployer(LocalWallet::new(&mut ethers::core::rand::thread_rng()));
+        let result = engine.deploy_contract(&artifact(""), &Value::Null, &ONE_WEI, None).await;
+        assert!(matches!(result, Err(DeployError::InvalidBytecode(_))));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_deploy_to_anvil() {
+        let anvil = Anvil::new().spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint()).with_deployer(wallet);
+
+        let fees = engine.fee_params(FeeStrategy::Normal).await.unwrap();
+        let result = engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &fees, None).await.unwrap();
+        assert!(result.block_number >= 1);
+        assert!(result.gas_used > 0);
+
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let code = provider.get_code(result.address, None).await.unwrap();
+        assert_eq!(hex::encode(code), "602a60005260206000f3");
+    }
+
+    /// Waits up to ten seconds for an event matching `wanted`, skipping the others.
+    async fn wait_for(
+        received: &mut tokio::sync::mpsc::UnboundedReceiver<DeployEvent>,
+        wanted: impl Fn(&DeployEvent) -> bool,
+    ) -> DeployEvent {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let event = received.recv().await.expect("deployment ended early");
+                if wanted(&event) {
+                    return event;
+                }
+            }
+        }).await.expect("event not received in time")
+    }
+
+    /// Mines a block every 50ms until `deploy` finishes, and returns its result.
+    async fn mine_until_done(
+        provider: &Provider<Http>,
+        mut deploy: tokio::task::JoinHandle<Result<DeploymentResult, DeployError>>,
+    ) -> Result<DeploymentResult, DeployError> {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let _: Value = provider.request("evm_mine", ()).await.unwrap();
+                tokio::select! {
+                    result = &mut deploy => return result.unwrap(),
+                    _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+                }
+            }
+        }).await.expect("deployment not confirmed in time")
+    }
+
+    #[test]
+    fn test_bump_fees() {
+        let mut legacy = priced_transaction(None, Vec::new(), &FeeParams::Legacy { gas_price: U256::from(100) });
+        bump_fees(&mut legacy, 20);
+        assert_eq!(legacy.gas_price(), Some(U256::from(121)));
+
+        let fees = FeeParams::Eip1559 {
+            base_fee_per_gas: U256::from(50),
+            max_fee_per_gas: U256::from(200),
+            max_priority_fee_per_gas: U256::zero(),
+        };
+        let mut tx = priced_transaction(None, Vec::new(), &fees);
+        bump_fees(&mut tx, 10);
+        let TypedTransaction::Eip1559(tx) = tx else { panic!("expected an EIP-1559 transaction") };
+        assert_eq!((tx.max_fee_per_gas, tx.max_priority_fee_per_gas), (Some(U256::from(221)), Some(U256::one())));
+    }
+
+    #[test]
+    fn test_send_errors_are_classified() {
+        assert!(is_nonce_error("(code: -32000, message: nonce too low, data: None)"));
+        assert!(is_nonce_error("Replacement transaction underpriced"));
+        assert!(!is_nonce_error("invalid nonce signature"));
+        assert!(!is_nonce_error("already known"));
+        assert!(is_already_known("(code: -32000, message: already known, data: None)"));
+        assert!(!is_already_known("nonce too low"));
+    }
+
+    /// Serves JSON-RPC from scripted results: each call to a method answers with the
+    /// next of its results, and the last one once they run out. A result made by
+    /// [`rpc_error`] is answered as an error. Returns the node's URL and every request
+    /// it received.
+    async fn scripted_node(script: Vec<(&'static str, Vec<Value>)>) -> (String, Arc<std::sync::Mutex<Vec<Value>>>) {
+        let script: HashMap<&str, VecDeque<Value>> =
+            script.into_iter().map(|(method, results)| (method, results.into())).collect();
+        let script = Arc::new(std::sync::Mutex::new(script));
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+        let node = axum::Router::new().route("/", axum::routing::post(move |axum::Json(request): axum::Json<Value>| {
+            let (script, received) = (Arc::clone(&script), Arc::clone(&received));
+            async move {
+                received.lock().unwrap().push(request.clone());
+                let mut script = script.lock().unwrap();
+                let method = request["method"].as_str().unwrap_or_default();
+                let results = script.get_mut(method).unwrap_or_else(|| panic!("unscripted call to {}", method));
+                let result = if results.len() > 1 { results.pop_front().unwrap() } else { results[0].clone() };
+                match result.get("error") {
+                    Some(error) => axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "error": error })),
+                    None => axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })),
+                }
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, node).await.unwrap() });
+        (url, requests)
+    }
+
+    fn rpc_error(message: &str) -> Value {
+        serde_json::json!({ "error": { "code": -32000, "message": message } })
+    }
+
+    fn mined_receipt(tx_hash: TxHash, block_number: u64) -> Value {
+        serde_json::to_value(TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some(block_number.into()),
+            block_hash: Some(H256::from_low_u64_be(block_number)),
+            status: Some(1.into()),
+            ..Default::default()
+        }).unwrap()
+    }
+
+    fn scripted_engine(url: String) -> DeployEngine {
+        DeployEngine::new("solc".to_string(), url)
+            .with_deployer(LocalWallet::new(&mut ethers::core::rand::thread_rng()))
+            .with_tracking(TrackingConfig { confirmations: 2, poll_interval: Duration::from_millis(1), ..Default::default() })
+    }
+
+    #[tokio::test]
+    async fn test_track_counts_confirmations_again_after_a_reorg() {
+        let tx_hash = TxHash::repeat_byte(7);
+        // Mined in block 5, reorged out, then mined again in block 6.
+        let (url, _) = scripted_node(vec![
+            ("eth_getTransactionReceipt", vec![mined_receipt(tx_hash, 5), Value::Null, mined_receipt(tx_hash, 6)]),
+            ("eth_blockNumber", vec!["0x5".into(), "0x6".into(), "0x7".into()]),
+        ]).await;
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+
+        let receipt = scripted_engine(url).confirm_sent("A.sol:A", U256::zero(), vec![tx_hash], Some(&events)).await.unwrap();
+        assert_eq!(receipt.unwrap().block_number, Some(6.into()));
+        let progress: Vec<(u64, u64)> = std::iter::from_fn(|| received.try_recv().ok())
+            .map(|event| match event {
+                DeployEvent::Confirmation { block_number, confirmations, .. } => (block_number, confirmations),
+                DeployEvent::Reorged { block_number, .. } => (block_number, 0),
+                DeployEvent::Confirmed { block_number, .. } => (block_number, u64::MAX),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(progress, [(5, 1), (5, 0), (6, 1), (6, 2), (6, u64::MAX)]);
+    }
+
+    #[tokio::test]
+    async fn test_track_gives_up_on_a_forgotten_transaction() {
+        // The node knows neither a receipt nor the transaction, and the nonce is used.
+        let (url, _) = scripted_node(vec![
+            ("eth_getTransactionReceipt", vec![Value::Null]),
+            ("eth_getTransactionCount", vec!["0x1".into()]),
+            ("eth_getTransactionByHash", vec![Value::Null]),
+        ]).await;
+        let receipt = scripted_engine(url).confirm_sent("A.sol:A", U256::zero(), vec![TxHash::repeat_byte(7)], None).await;
+        assert!(receipt.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_replacement_keeps_tracking_the_original() {
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let mut tx = priced_transaction(None, hex::decode(RETURN_42_INIT_CODE).unwrap(), &ONE_WEI);
+        tx.set_nonce(0).set_gas(100_000).set_chain_id(1);
+        let raw = tx.rlp_signed(&wallet.sign_transaction(&tx).await.unwrap());
+        let original = H256::from(ethers::utils::keccak256(&raw));
+        // Still pending when the replacement is sent, which the node refuses; mined afterwards.
+        let (url, _) = scripted_node(vec![
+            ("eth_getTransactionReceipt", vec![Value::Null, mined_receipt(original, 5)]),
+            ("eth_getTransactionCount", vec!["0x0".into()]),
+            ("eth_getTransactionByHash", vec![serde_json::to_value(Transaction::default()).unwrap()]),
+            ("eth_sendRawTransaction", vec![rpc_error("replacement transaction underpriced")]),
+            ("eth_blockNumber", vec!["0x6".into()]),
+        ]).await;
+        let engine = scripted_engine(url).with_tracking(TrackingConfig {
+            confirmations: 2,
+            poll_interval: Duration::from_millis(1),
+            stuck_timeout: Some(Duration::ZERO),
+            max_replacements: 1,
+            ..Default::default()
+        });
+        let tracked = Tracked {
+            label: "A.sol:A".to_string(),
+            from: wallet.address(),
+            nonce: U256::zero(),
+            hashes: vec![original],
+            resend: Some(Resendable { wallet, tx, raw }),
+        };
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+
+        let receipt = engine.track(tracked, Some(&events)).await.unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, original);
+        assert!(std::iter::from_fn(|| received.try_recv().ok()).all(|event| !matches!(event, DeployEvent::Replaced { .. })));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_confirmations_survive_a_reorg_on_anvil() {
+        let anvil = Anvil::new().arg("--no-mining").spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint())
+            .with_deployer(wallet)
+            .with_tracking(TrackingConfig { confirmations: 3, poll_interval: Duration::from_millis(20), ..Default::default() });
+        let fees = engine.fee_params(FeeStrategy::Normal).await.unwrap();
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let deploy = tokio::spawn(async move {
+            engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &fees, Some(&events)).await
+        });
+
+        wait_for(&mut received, |event| matches!(event, DeployEvent::Confirming { .. })).await;
+        let snapshot: U256 = provider.request("evm_snapshot", ()).await.unwrap();
+        let _: Value = provider.request("evm_mine", ()).await.unwrap();
+        let first = wait_for(&mut received, |event| matches!(event, DeployEvent::Confirmation { .. })).await;
+        assert!(matches!(first, DeployEvent::Confirmation { confirmations: 1, required: 3, .. }));
+
+        // Dropping the block that holds the transaction is a reorg; it is mined again afterwards.
+        let reverted: bool = provider.request("evm_revert", [snapshot]).await.unwrap();
+        assert!(reverted);
+        wait_for(&mut received, |event| matches!(event, DeployEvent::Reorged { .. })).await;
+        let result = mine_until_done(&provider, deploy).await.unwrap();
+
+        let code = provider.get_code(result.address, None).await.unwrap();
+        assert_eq!(hex::encode(code), "602a60005260206000f3");
+        let latest = provider.get_block_number().await.unwrap().as_u64();
+        assert!(latest + 1 - result.block_number >= 3);
+        let confirmed = wait_for(&mut received, |event| matches!(event, DeployEvent::Confirmed { .. })).await;
+        assert!(matches!(confirmed, DeployEvent::Confirmed { tx_hash, .. } if tx_hash == result.tx_hash));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_stuck_transaction_is_replaced_on_anvil() {
+        let anvil = Anvil::new().arg("--no-mining").spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint())
+            .with_deployer(wallet)
+            .with_tracking(TrackingConfig {
+                poll_interval: Duration::from_millis(20),
+                stuck_timeout: Some(Duration::from_millis(200)),
+                max_replacements: 1,
+                ..Default::default()
+            });
+        let fees = engine.fee_params(FeeStrategy::Normal).await.unwrap();
+        let original_price = fees.max_price_per_gas();
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let deploy = tokio::spawn(async move {
+            engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &fees, Some(&events)).await
+        });
+
+        let DeployEvent::Broadcasting { tx_hash: original, nonce, .. } =
+            wait_for(&mut received, |event| matches!(event, DeployEvent::Broadcasting { .. })).await
+        else {
+            unreachable!()
+        };
+        let DeployEvent::Replaced { replaced, tx_hash: replacement, nonce: replaced_nonce, .. } =
+            wait_for(&mut received, |event| matches!(event, DeployEvent::Replaced { .. })).await
+        else {
+            unreachable!()
+        };
+        assert_eq!((replaced, replaced_nonce), (original, nonce));
+
+        let result = mine_until_done(&provider, deploy).await.unwrap();
+        assert_eq!(result.tx_hash, replacement);
+        let replacement_tx = provider.get_transaction(replacement).await.unwrap().unwrap();
+        assert!(replacement_tx.max_fee_per_gas.or(replacement_tx.gas_price).unwrap() > original_price);
+        assert!(provider.get_transaction_receipt(original).await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_deployments_get_consecutive_nonces() {
+        let mut receipt = mined_receipt(TxHash::repeat_byte(7), 1);
+        receipt["contractAddress"] = serde_json::json!(Address::repeat_byte(9));
+        let (url, requests) = scripted_node(vec![
+            ("eth_chainId", vec!["0x7a69".into()]),
+            ("eth_getTransactionCount", vec!["0x5".into()]),
+            ("eth_estimateGas", vec!["0x186a0".into()]),
+            ("eth_sendRawTransaction", vec![serde_json::json!(TxHash::repeat_byte(7))]),
+            ("eth_getTransactionReceipt", vec![receipt]),
+            ("eth_blockNumber", vec!["0x2".into()]),
+        ]).await;
+        let engine = Arc::new(scripted_engine(url));
+
+        let deploys: Vec<_> = (0..20).map(|_| {
+            let engine = Arc::clone(&engine);
+            tokio::spawn(async move { engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &ONE_WEI, None).await })
+        }).collect();
+        for deploy in deploys {
+            deploy.await.unwrap().unwrap();
+        }
+
+        let requests = requests.lock().unwrap();
+        let calls = |method: &'static str| requests.iter().filter(move |request| request["method"] == method);
+        let mut nonces: Vec<u64> = calls("eth_sendRawTransaction")
+            .map(|request| {
+                let raw = hex::decode(request["params"][0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+                tx.nonce().unwrap().as_u64()
+            })
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, (5..25).collect::<Vec<_>>());
+        // The pending count is read once, by whichever deployment reserved first.
+        assert_eq!(calls("eth_getTransactionCount").count(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_concurrent_deployments_get_consecutive_nonces_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let from = wallet.address();
+        let engine = Arc::new(DeployEngine::new("solc".to_string(), anvil.endpoint())
+            .with_deployer(wallet.clone())
+            .with_tracking(TrackingConfig { poll_interval: Duration::from_millis(20), ..Default::default() }));
+        let fees = engine.fee_params(FeeStrategy::Normal).await.unwrap();
+        let start = provider.get_transaction_count(from, None).await.unwrap();
+
+        let deploys: Vec<_> = (0..20).map(|_| {
+            let engine = Arc::clone(&engine);
+            tokio::spawn(async move { engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &fees, None).await })
+        }).collect();
+        let mut nonces = Vec::new();
+        for deploy in deploys {
+            let result = deploy.await.unwrap().unwrap();
+            nonces.push(provider.get_transaction(result.tx_hash).await.unwrap().unwrap().nonce);
+        }
+        nonces.sort();
+        assert_eq!(nonces, (0..20u64).map(|i| start + i).collect::<Vec<_>>());
+
+        // A transaction sent from the same key behind the manager's back takes its next
+        // nonce; the node's rejection makes the engine resync and use the one after.
+        let chain_id = provider.get_chainid().await.unwrap().as_u64();
+        let outside = SignerMiddleware::new(provider.clone(), wallet.with_chain_id(chain_id));
+        let tx = TransactionRequest::new().to(Address::repeat_byte(0x42)).value(1u64).nonce(start + 20);
+        outside.send_transaction(tx, None).await.unwrap().await.unwrap();
+        let result = engine.deploy_contract(&artifact(RETURN_42_INIT_CODE), &Value::Null, &fees, None).await.unwrap();
+        let nonce = provider.get_transaction(result.tx_hash).await.unwrap().unwrap().nonce;
+        assert_eq!(nonce, start + 21);
+    }
+
+    /// solc's placeholder for `qualified_name`.
+    fn placeholder(qualified_name: &str) -> String {
+        format!("__${}$__", &hex::encode(ethers::utils::keccak256(qualified_name))[..34])
+    }
+
+    /// `Vault` links against the library `Math`, which links against the library `Util`.
+    /// Linked code starts with `PUSH20 <library> POP`, followed by init code for the
+    /// runtime returning 42.
+    fn linking_compilation() -> Compilation {
+        let linking = |source: &str, name: &str, kind: ContractKind, library: Option<&str>| {
+            let mut artifact = CompiledArtifact {
+                contract_name: name.to_string(),
+                source_name: source.to_string(),
+                kind,
+                raw_abi: serde_json::json!([]),
+                bytecode: RETURN_42_INIT_CODE.to_string(),
+                ..Default::default()
+            };
+            if let Some(library) = library {
+                let (file, library_name) = library.split_once(':').unwrap();
+                artifact.bytecode = format!("73{}50600a6022600039600a6000f3602a60005260206000f3", placeholder(library));
+                artifact.link_references.entry(file.to_string()).or_default()
+                    .insert(library_name.to_string(), vec![BytecodeRange { start: 1, length: 20 }]);
+            }
+            artifact
+        };
+        Compilation {
+            main: linking("Vault.sol", "Vault", ContractKind::Contract, Some("Vault.sol:Math")),
+            libraries: vec![
+                linking("Vault.sol", "Math", ContractKind::Library, Some("lib/Util.sol:Util")),
+                linking("lib/Util.sol", "Util", ContractKind::Library, None),
+            ],
+            helpers: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_link_plan_orders_dependencies() {
+        let compilation = linking_compilation();
+        let util: Address = "0x1111111111111111111111111111111111111111".parse().unwrap();
+        let math: Address = "0x2222222222222222222222222222222222222222".parse().unwrap();
+        let engine = DeployEngine::new("solc".to_string(), "http://127.0.0.1:1".to_string());
+        let summary = |plan: LinkPlan| plan.into_iter()
+            .map(|(library, known)| (library.contract_name.clone(), known))
+            .collect::<Vec<_>>();
+
+        let plan = engine.link_plan(&compilation, &|_| None).unwrap();
+        assert_eq!(summary(plan), [("Util".to_string(), None), ("Math".to_string(), None)]);
+
+        let plan = engine.link_plan(&compilation, &|library| (library.contract_name == "Util").then_some(util)).unwrap();
+        assert_eq!(summary(plan), [
+            ("Util".to_string(), Some((util, LibrarySource::Stored))),
+            ("Math".to_string(), None),
+        ]);
+
+        // A configured library is linked as is, so its own dependencies are not needed.
+        let engine = engine.with_libraries(BTreeMap::from([("Math".to_string(), math)]));
+        let plan = engine.link_plan(&compilation, &|_| Some(util)).unwrap();
+        assert_eq!(summary(plan), [("Math".to_string(), Some((math, LibrarySource::Configured)))]);
+    }
+
+    #[test]
+    fn test_link_bytecode() {
+        let vault = linking_compilation().main;
+        let math: Address = "0x2222222222222222222222222222222222222222".parse().unwrap();
+        assert!(matches!(build_creation_code(&vault, &Value::Null), Err(DeployError::LinkError(_))));
+
+        let addresses = BTreeMap::from([("Vault.sol:Math".to_string(), math)]);
+        let linked = link_bytecode(&vault.bytecode, &vault.link_references, &addresses).unwrap();
+        assert_eq!(&linked[..44], format!("73{}50", "22".repeat(20)));
+        assert!(!linked.contains("__$"));
+
+        match link_bytecode(&vault.bytecode, &vault.link_references, &BTreeMap::new()) {
+            Err(DeployError::LinkError(msg)) => assert!(msg.contains("Vault.sol:Math"), "{}", msg),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_deploy_linked_to_anvil() {
+        let anvil = Anvil::new().spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint()).with_deployer(wallet);
+        let compilation = linking_compilation();
+
+        let first = engine.deploy_linked(&compilation, &Value::Null, &DeployOptions::default(), |_| None).await.unwrap();
+        let sources: Vec<_> = first.libraries.iter().map(|step| (step.library.as_str(), step.source)).collect();
+        assert_eq!(sources, [("lib/Util.sol:Util", LibrarySource::Deployed), ("Vault.sol:Math", LibrarySource::Deployed)]);
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        assert_eq!(hex::encode(provider.get_code(first.address, None).await.unwrap()), "602a60005260206000f3");
+
+        // Reusing the stored Math needs no further library deployments.
+        let math = first.libraries[1].address;
+        let second = engine.deploy_linked(&compilation, &Value::Null, &DeployOptions::default(), |library| {
+            (library.contract_name == "Math").then_some(math)
+        }).await.unwrap();
+        assert_eq!(second.libraries.len(), 1);
+        assert_eq!((second.libraries[0].address, second.libraries[0].source), (math, LibrarySource::Stored));
+
+        // An address without code is rejected rather than linked.
+        let empty = Address::repeat_byte(0x42);
+        let result = engine.deploy_linked(&compilation, &Value::Null, &DeployOptions::default(), |_| Some(empty)).await;
+        assert!(matches!(result, Err(DeployError::LinkError(_))));
+    }
+
+    #[test]
+    fn test_signer_sources() {
+        // anvil's first two dev accounts, and the mnemonic they are derived from.
+        const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        const MNEMONIC: &str = "test test test test test test test test test test test junk";
+        let first: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+        let second: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let secret = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            Some(path)
+        };
+
+        LocalWallet::encrypt_keystore(dir.path(), &mut ethers::core::rand::thread_rng(), hex::decode(KEY).unwrap(), "hunter2", Some("deployer.json")).unwrap();
+        let keystore = SignerSource::Keystore {
+            path: dir.path().join("deployer.json"),
+            password_env: None,
+            password_file: secret("password", "hunter2\n"),
+        };
+        assert_eq!(keystore.load(false).unwrap().address(), first);
+        let wrong = SignerSource::Keystore {
+            path: dir.path().join("deployer.json"),
+            password_env: None,
+            password_file: secret("wrong", "hunter3"),
+        };
+        assert!(matches!(wrong.load(false), Err(DeployError::SignerError(_))));
+
+        let mnemonic = SignerSource::Mnemonic {
+            mnemonic_env: None,
+            mnemonic_file: secret("mnemonic", MNEMONIC),
+            derivation_path: DEFAULT_DERIVATION_PATH.to_string(),
+            index: 1,
+        };
+        assert_eq!(mnemonic.load(false).unwrap().address(), second);
+        let typo = SignerSource::Mnemonic {
+            mnemonic_env: None,
+            mnemonic_file: secret("typo", "test test hunter2 junk"),
+            derivation_path: DEFAULT_DERIVATION_PATH.to_string(),
+            index: 0,
+        };
+        let err = typo.load(false).unwrap_err().to_string();
+        assert!(!err.contains("hunter2"), "{}", err);
+
+        let raw = SignerSource::PrivateKey { key_env: None, key_file: secret("key", KEY) };
+        assert!(raw.load(false).unwrap_err().to_string().contains("dev mode"));
+        assert_eq!(raw.load(true).unwrap().address(), first);
+        let ambiguous = SignerSource::PrivateKey { key_env: Some("DEPLOYER_PRIVATE_KEY".to_string()), key_file: secret("key", KEY) };
+        assert!(ambiguous.check(true).is_err());
+    }
+
+    #[test]
+    fn test_fee_strategy_json() {
+        assert_eq!(serde_json::from_str::<FeeStrategy>(r#"{"strategy":"fast"}"#).unwrap(), FeeStrategy::Fast);
+        let fixed: FeeStrategy = serde_json::from_str(
+            r#"{"strategy":"fixed","max_fee_per_gas":"30000000000","max_priority_fee_per_gas":1000000000}"#
+        ).unwrap();
+        assert_eq!(fixed, FeeStrategy::Fixed {
+            max_fee_per_gas: U256::from(30_000_000_000u64),
+            max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+        });
+        assert!(serde_json::from_str::<FeeStrategy>(r#"{"strategy":"fixed","max_fee_per_gas":"lots","max_priority_fee_per_gas":1}"#).is_err());
+
+        // Wei amounts are written as decimal strings.
+        let fees = serde_json::to_value(FeeParams::Legacy { gas_price: U256::exp10(20) }).unwrap();
+        assert_eq!(fees, serde_json::json!({"type": "legacy", "gas_price": "100000000000000000000"}));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_estimate_and_cost_limit_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint()).with_deployer(wallet);
+        let compilation = linking_compilation();
+        let options = DeployOptions { fee: FeeStrategy::Fast, ..Default::default() };
+
+        // Both libraries still need deploying, so three transactions are estimated.
+        let estimate = engine.estimate_deployment(&compilation, &Value::Null, &options, |_| None).await.unwrap();
+        let contracts: Vec<_> = estimate.transactions.iter().map(|tx| tx.contract.as_str()).collect();
+        assert_eq!(contracts, ["lib/Util.sol:Util", "Vault.sol:Math", "Vault.sol:Vault"]);
+        assert!(matches!(estimate.fees, FeeParams::Eip1559 { .. }));
+        assert_eq!(estimate.total_cost_wei, estimate.fees.max_price_per_gas() * estimate.gas_limit);
+
+        let fixed = FeeStrategy::Fixed { max_fee_per_gas: U256::from(7), max_priority_fee_per_gas: U256::from(3) };
+        let legacy = DeployEngine::new("solc".to_string(), anvil.endpoint()).with_legacy_fees(true);
+        assert_eq!(legacy.fee_params(fixed).await.unwrap(), FeeParams::Legacy { gas_price: U256::from(7) });
+
+        // A deployment over the limit is rejected before any transaction is sent.
+        let capped = engine.with_max_deploy_cost(estimate.total_cost_wei - 1);
+        let result = capped.deploy_linked(&compilation, &Value::Null, &options, |_| None).await;
+        assert!(matches!(result, Err(DeployError::CostLimitExceeded(_))));
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        assert_eq!(provider.get_transaction_count(anvil.addresses()[0], None).await.unwrap(), U256::zero());
+    }
+
+    #[test]
+    fn test_parse_salt() {
+        let raw = format!("0x{}", "ab".repeat(32));
+        assert_eq!(parse_salt(&raw).unwrap(), H256::repeat_byte(0xab));
+        assert_eq!(parse_salt("vesting-alice").unwrap(), H256::from(ethers::utils::keccak256("vesting-alice")));
+        assert!(parse_salt("0x1234").is_err());
+        assert!(parse_salt("").is_err());
+    }
+
+    #[test]
+    fn test_predict_address() {
+        // EIP-1014 example 0: factory 0x0, salt 0x0, init code 0x00.
+        let engine = DeployEngine::new("solc".to_string(), "http://127.0.0.1:1".to_string())
+            .with_create2_factory(Address::zero());
+        let compilation = Compilation {
+            main: artifact("00"),
+            libraries: Vec::new(),
+            helpers: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        let prediction = engine.predict_address(&compilation, &Value::Null, H256::zero(), |_| None).unwrap();
+        assert_eq!(prediction.address, "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38".parse().unwrap());
+        assert_eq!(prediction.create2.init_code_hash, H256::from(ethers::utils::keccak256([0u8])));
+
+        // Libraries must already have an address for the prediction to hold.
+        let compilation = linking_compilation();
+        let result = engine.predict_address(&compilation, &Value::Null, H256::zero(), |_| None);
+        assert!(matches!(result, Err(DeployError::LinkError(_))));
+        let math: Address = "0x2222222222222222222222222222222222222222".parse().unwrap();
+        let prediction = engine.predict_address(&compilation, &Value::Null, H256::zero(), |library| {
+            (library.contract_name == "Math").then_some(math)
+        }).unwrap();
+        assert_eq!((prediction.libraries.len(), prediction.libraries[0].source), (1, LibrarySource::Stored));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil"]
+    async fn test_create2_deployment_matches_prediction() {
+        // anvil predeploys the factory at DEFAULT_CREATE2_FACTORY.
+        let anvil = Anvil::new().spawn();
+        let wallet: LocalWallet = anvil.keys()[0].clone().into();
+        let engine = DeployEngine::new("solc".to_string(), anvil.endpoint()).with_deployer(wallet);
+        let compilation = Compilation {
+            main: artifact(RETURN_42_INIT_CODE),
+            libraries: Vec::new(),
+            helpers: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        let salt = parse_salt("vesting-alice").unwrap();
+        let create2 = DeployOptions { salt: Some(salt), ..Default::default() };
+
+        let prediction = engine.predict_address(&compilation, &Value::Null, salt, |_| None).unwrap();
+        let deployment = engine.deploy_linked(&compilation, &Value::Null, &create2, |_| None).await.unwrap();
+        assert_eq!(deployment.address, prediction.address);
+        assert_eq!(deployment.deployer, anvil.addresses()[0]);
+        assert_eq!(deployment.create2.unwrap().salt, salt);
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        assert_eq!(hex::encode(provider.get_code(prediction.address, None).await.unwrap()), "602a60005260206000f3");
+
+        let again = engine.deploy_linked(&compilation, &Value::Null, &create2, |_| None).await;
+        assert!(matches!(again, Err(DeployError::Create2Error(_))));
+    }
+
+    const VESTING_CONSTRUCTOR_ABI: &str = r#"[{"type":"constructor","stateMutability":"nonpayable","inputs":[
+        {"name":"_token_address","type":"address","internalType":"address"},
+        {"name":"_beneficiary","type":"address","internalType":"address"},
+        {"name":"_start_time","type":"uint256","internalType":"uint256"},
+        {"name":"_cliff_duration","type":"uint256","internalType":"uint256"},
+        {"name":"_duration","type":"uint256","internalType":"uint256"},
+        {"name":"_initial_owner","type":"address","internalType":"address"}]}]"#;
+
+    fn raw_abi(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn vesting_params() -> Value {
+        serde_json::json!({
+            "token_address": "0x1111111111111111111111111111111111111111",
+            "beneficiary": "0x2222222222222222222222222222222222222222",
+            "start_time": 1700000000,
+            "cliff_duration": "86400",
+            "duration": "0x01e13380",
+            "initial_owner": "0x3333333333333333333333333333333333333333",
+        })
+    }
+
+    #[test]
+    fn test_encode_vesting_constructor_args() {
+        let encoded = encode_constructor_args(&raw_abi(VESTING_CONSTRUCTOR_ABI), &vesting_params()).unwrap();
+        let expected = ethers::abi::encode(&[
+            Token::Address("0x1111111111111111111111111111111111111111".parse().unwrap()),
+            Token::Address("0x2222222222222222222222222222222222222222".parse().unwrap()),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::Uint(U256::from(86_400u64)),
+            Token::Uint(U256::from(31_536_000u64)),
+            Token::Address("0x3333333333333333333333333333333333333333".parse().unwrap()),
+        ]);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_creation_code_appends_args() {
+        let mut vesting = artifact(RETURN_42_INIT_CODE);
+        vesting.raw_abi = raw_abi(VESTING_CONSTRUCTOR_ABI);
+        let code = build_creation_code(&vesting, &vesting_params()).unwrap();
+        assert_eq!(code.len(), RETURN_42_INIT_CODE.len() / 2 + 6 * 32);
+        assert!(hex::encode(&code).starts_with(RETURN_42_INIT_CODE));
+    }
+
+    #[test]
+    fn test_encode_reports_failing_param() {
+        let mut params = vesting_params();
+        params["beneficiary"] = serde_json::json!("0x1234");
+        match encode_constructor_args(&raw_abi(VESTING_CONSTRUCTOR_ABI), &params) {
+            Err(DeployError::ConstructorArgError { param, .. }) => assert_eq!(param, "_beneficiary"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        params.as_object_mut().unwrap().remove("duration");
+        params["beneficiary"] = serde_json::json!("0x2222222222222222222222222222222222222222");
+        match encode_constructor_args(&raw_abi(VESTING_CONSTRUCTOR_ABI), &params) {
+            Err(DeployError::ConstructorArgError { param, .. }) => assert_eq!(param, "_duration"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_encode_no_constructor() {
+        assert!(encode_constructor_args(&raw_abi("[]"), &Value::Null).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encode_nested_types() {
+        let abi = &raw_abi(r#"[{"type":"constructor","inputs":[
+            {"name":"flag","type":"bool"},
+            {"name":"delta","type":"int8"},
+            {"name":"data","type":"bytes"},
+            {"name":"tag","type":"bytes4"},
+            {"name":"amounts","type":"uint16[2]"},
+            {"name":"grants","type":"tuple[]","components":[
+                {"name":"to","type":"address"},{"name":"amount","type":"uint256"}]}]}]"#);
+        let params = serde_json::json!({
+            "flag": true,
+            "delta": -128,
+            "data": "0xdeadbeef",
+            "tag": "0xcafebabe",
+            "amounts": [1, "65535"],
+            "grants": [
+                {"to": "0x1111111111111111111111111111111111111111", "amount": 5},
+                ["0x2222222222222222222222222222222222222222", "6"]
+            ],
+        });
+        let encoded = encode_constructor_args(abi, &params).unwrap();
+        let decoded = ethers::abi::decode(
+            &[
+                ParamType::Bool,
+                ParamType::Int(8),
+                ParamType::Bytes,
+                ParamType::FixedBytes(4),
+                ParamType::FixedArray(Box::new(ParamType::Uint(16)), 2),
+                ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(256)]))),
+            ],
+            &encoded,
+        ).unwrap();
+        assert_eq!(decoded[1], Token::Int(I256::from(-128).into_raw()));
+        assert_eq!(decoded[5], Token::Array(vec![
+            Token::Tuple(vec![
+                Token::Address("0x1111111111111111111111111111111111111111".parse().unwrap()),
+                Token::Uint(U256::from(5)),
+            ]),
+            Token::Tuple(vec![
+                Token::Address("0x2222222222222222222222222222222222222222".parse().unwrap()),
+                Token::Uint(U256::from(6)),
+            ]),
+        ]));
+
+        let mut bad = params.clone();
+        bad["amounts"] = serde_json::json!([1, 65536]);
+        match encode_constructor_args(abi, &bad) {
+            Err(DeployError::ConstructorArgError { param, .. }) => assert_eq!(param, "amounts[1]"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut bad = params.clone();
+        bad["grants"][0]["amount"] = serde_json::json!(-1);
+        match encode_constructor_args(abi, &bad) {
+            Err(DeployError::ConstructorArgError { param, .. }) => assert_eq!(param, "grants[0].amount"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let mut bad = params;
+        bad["delta"] = serde_json::json!(128);
+        assert!(encode_constructor_args(abi, &bad).is_err());
+    }
+
+    fn resolver(base_path: &Path) -> ImportResolver {
+        ImportResolver::new(base_path.to_path_buf(), &[], Vec::new())
+    }
+
+    /// Writes an executable script standing in for solc that prints `stdout`.
+    #[cfg(unix)]
+    fn fake_solc(dir: &Path, stdout: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("solc");
+        std::fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\ncat <<'EOF'\n{}\nEOF\n", stdout)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_returns_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), &serde_json::json!({
+            "errors": [{
+                "errorCode": "2072",
+                "message": "Unused local variable.",
+                "severity": "warning",
+                "sourceLocation": { "file": "Return42.sol", "start": 24, "end": 30 },
+                "type": "Warning"
+            }],
+            "contracts": { "Return42.sol": { "Return42": {
+                "abi": [],
+                "evm": {
+                    "bytecode": { "object": RETURN_42_INIT_CODE },
+                    "deployedBytecode": { "object": "602a60005260206000f3" }
+                }
+            }}}
+        }).to_string());
+        let engine = DeployEngine::new(solc, "http://127.0.0.1:1".to_string());
+        let source = "pragma solidity ^0.8.0;\ncontract Return42 {}\n";
+        let settings = CompilerSettings { evm_version: Some(EvmVersion::Paris), ..CompilerSettings::default() };
+        let compilation = engine.compile_solidity(source, "Return42.sol", "Return42", &resolver(dir.path()), &settings).unwrap();
+        let artifact = &compilation.main;
+        assert_eq!(artifact.compiler_settings.evm_version, Some(EvmVersion::Paris));
+        assert_eq!(artifact.compiler_settings.runs, Some(200));
+        assert_eq!(artifact.bytecode, RETURN_42_INIT_CODE);
+        assert_eq!(artifact.deployed_bytecode, "602a60005260206000f3");
+        assert_eq!(compilation.diagnostics.len(), 1);
+        assert_eq!(compilation.diagnostics[0].location.as_ref().unwrap().line, Some(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_returns_full_artifact() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = r#"{"compiler":{"version":"0.8.19+commit.7dd6d404"},"language":"Solidity"}"#;
+        let solc = fake_solc(dir.path(), &serde_json::json!({
+            "contracts": { "Counter.sol": { "Counter": {
+                "abi": [{"type":"function","name":"count","inputs":[],"outputs":[{"name":"","type":"uint256"}],"stateMutability":"view"}],
+                "metadata": metadata,
+                "storageLayout": { "storage": [{ "label": "count", "slot": "0", "offset": 0, "type": "t_uint256" }], "types": {} },
+                "evm": {
+                    "bytecode": { "object": RETURN_42_INIT_CODE, "sourceMap": "0:10:0:-:0" },
+                    "deployedBytecode": {
+                        "object": "602a60005260206000f3",
+                        "sourceMap": "0:10:0:-:0;;",
+                        "immutableReferences": { "7": [{ "start": 1, "length": 32 }] }
+                    },
+                    "methodIdentifiers": { "count()": "06661abd" }
+                }
+            }}}
+        }).to_string());
+        let engine = DeployEngine::new(solc, "http://127.0.0.1:1".to_string());
+        let source = "pragma solidity ^0.8.0;\ncontract Counter {}\n";
+        let artifact = engine.compile_solidity(source, "Counter.sol", "Counter", &resolver(dir.path()), &CompilerSettings::default()).unwrap().main;
+
+        assert_eq!(artifact.abi.function("count").unwrap().short_signature(), [0x06, 0x66, 0x1a, 0xbd]);
+        assert_eq!(artifact.method_identifiers["count()"], "06661abd");
+        assert_eq!(artifact.metadata, metadata);
+        assert_eq!(artifact.compiler_version.as_deref(), Some("0.8.19+commit.7dd6d404"));
+        assert_eq!(artifact.storage_layout["storage"][0]["label"], "count");
+        assert_eq!(artifact.immutable_references["7"], vec![BytecodeRange { start: 1, length: 32 }]);
+        assert_eq!((artifact.source_map.as_str(), artifact.deployed_source_map.as_str()), ("0:10:0:-:0", "0:10:0:-:0;;"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_sorts_every_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let definition = |name: &str, kind: &str, is_abstract: bool| serde_json::json!({
+            "nodeType": "ContractDefinition", "name": name, "contractKind": kind, "abstract": is_abstract
+        });
+        let contract = |bytecode: &str| serde_json::json!({ "abi": [], "evm": { "bytecode": { "object": bytecode } } });
+        let solc = fake_solc(dir.path(), &serde_json::json!({
+            "sources": {
+                "Vault.sol": { "id": 0, "ast": { "nodes": [
+                    { "nodeType": "PragmaDirective" },
+                    definition("IVault", "interface", false),
+                    definition("Math", "library", false),
+                    definition("Base", "contract", true),
+                    definition("Vault", "contract", false),
+                ]}},
+                "lib/Util.sol": { "id": 1, "ast": { "nodes": [definition("Util", "library", false)] } }
+            },
+            "contracts": {
+                "Vault.sol": {
+                    "IVault": contract(""),
+                    "Math": contract("6001"),
+                    "Base": contract(""),
+                    "Vault": contract(RETURN_42_INIT_CODE),
+                },
+                "lib/Util.sol": { "Util": contract("6002") }
+            }
+        }).to_string());
+        let engine = DeployEngine::new(solc, "http://127.0.0.1:1".to_string());
+        let compile = |main: &str| engine.compile_solidity("contract Vault {}", "Vault.sol", main, &resolver(dir.path()), &CompilerSettings::default());
+
+        let compilation = compile("Vault").unwrap();
+        assert_eq!(compilation.main.contract_name, "Vault");
+        assert_eq!(compilation.main.kind, ContractKind::Contract);
+        let names = |artifacts: &[CompiledArtifact]| artifacts.iter()
+            .map(|a| format!("{}:{}:{:?}", a.source_name, a.contract_name, a.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(names(&compilation.libraries), ["Vault.sol:Math:Library", "lib/Util.sol:Util:Library"]);
+        assert_eq!(names(&compilation.helpers), ["Vault.sol:Base:Abstract", "Vault.sol:IVault:Interface"]);
+
+        // A library can be the main contract; an interface cannot be deployed.
+        assert_eq!(compile("Math").unwrap().libraries.len(), 1);
+        assert!(matches!(compile("IVault"), Err(DeployError::NoBytecodeFound(_))));
+        match compile("Util") {
+            Err(DeployError::NoAbiFound(msg)) => assert!(msg.contains("Base, IVault, Math, Vault"), "{}", msg),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_errors_are_structured() {
+        let dir = tempfile::tempdir().unwrap();
+        let solc = fake_solc(dir.path(), &serde_json::json!({
+            "errors": [{
+                "errorCode": "2314",
+                "message": "Expected ';' but got '}'",
+                "formattedMessage": "ParserError: Expected ';' but got '}'",
+                "severity": "error",
+                "sourceLocation": { "file": "Broken.sol", "start": 42, "end": 43 },
+                "type": "ParserError"
+            }]
+        }).to_string());
+        let engine = DeployEngine::new(solc, "http://127.0.0.1:1".to_string());
+        let source = "pragma solidity ^0.8.0;\ncontract Broken {\n    uint x\n}\n";
+        match engine.compile_solidity(source, "Broken.sol", "Broken", &resolver(dir.path()), &CompilerSettings::default()) {
+            Err(DeployError::CompilationFailed(diagnostics)) => {
+                let location = diagnostics[0].location.as_ref().unwrap();
+                assert_eq!(diagnostics[0].kind, "ParserError");
+                assert_eq!((location.line, location.column), (Some(3), Some(1)));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compiler_selected_from_pragma() {
+        let dir = tempfile::tempdir().unwrap();
+        // Each fake compiler emits its own version as the bytecode, e.g. 0x000819.
+        crate::solc_versions::tests::fake_compiler_cache(dir.path(), &["0.7.6", "0.8.19", "0.8.24"], &serde_json::json!({
+            "contracts": { "Versioned.sol": { "Versioned": {
+                "abi": [],
+                "evm": { "bytecode": { "object": "{version}" }, "deployedBytecode": { "object": "" } }
+            }}}
+        }).to_string());
+        let engine = DeployEngine::new("/nonexistent/solc".to_string(), "http://127.0.0.1:1".to_string())
+            .with_compiler_cache(Arc::new(CompilerCache::new(dir.path().to_path_buf())));
+
+        let compile = |pragma: &str| {
+            let source = format!("pragma solidity {};\ncontract Versioned {{}}\n", pragma);
+            engine.compile_solidity(&source, "Versioned.sol", "Versioned", &resolver(dir.path()), &CompilerSettings::default())
+        };
+        assert_eq!(compile("^0.8.0").unwrap().main.bytecode, "0.8.24");
+        assert_eq!(compile(">=0.8.0 <0.8.20").unwrap().main.bytecode, "0.8.19");
+        assert_eq!(compile("^0.7.0").unwrap().main.bytecode, "0.7.6");
+        assert!(matches!(compile("^0.6.0"), Err(DeployError::NoMatchingCompiler(_))));
+        assert!(matches!(compile("^banana"), Err(DeployError::InvalidPragma(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compile_cache_skips_solc() {
+        let dir = tempfile::tempdir().unwrap();
+        crate::solc_versions::tests::fake_compiler_cache(dir.path(), &["0.8.19"], &serde_json::json!({
+            "contracts": { "Cached.sol": { "Cached": {
+                "abi": [],
+                "evm": { "bytecode": { "object": "6000" }, "deployedBytecode": { "object": "" } }
+            }}}
+        }).to_string());
+        let cache = Arc::new(CompileCache::new(dir.path().join("cache"), 10).unwrap());
+        let engine = DeployEngine::new("/nonexistent/solc".to_string(), "http://127.0.0.1:1".to_string())
+            .with_compiler_cache(Arc::new(CompilerCache::new(dir.path().to_path_buf())))
+            .with_compile_cache(Arc::clone(&cache));
+
+        let source = "pragma solidity ^0.8.0;\ncontract Cached {}\n";
+        engine.compile_solidity(source, "Cached.sol", "Cached", &resolver(dir.path()), &CompilerSettings::default()).unwrap();
+        engine.compile_solidity(source, "Cached.sol", "Cached", &resolver(dir.path()), &CompilerSettings::default()).unwrap();
+        engine.compile_solidity(source, "Cached.sol", "Cached", &ImportResolver::new(dir.path().to_path_buf(), &[], vec!["a/=b/".to_string()]), &CompilerSettings::default()).unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+    }
+
+    #[test]
+    fn test_unresolved_imports_stop_compilation() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = DeployEngine::new("/nonexistent/solc".to_string(), "http://127.0.0.1:1".to_string());
+        let source = "pragma solidity ^0.8.0;\nimport \"@openzeppelin/contracts/access/Ownable.sol\";\ncontract A {}\n";
+        match engine.compile_solidity(source, "A.sol", "A", &resolver(dir.path()), &CompilerSettings::default()) {
+            Err(DeployError::UnresolvedImports(imports)) => {
+                assert_eq!(imports[0].path, "@openzeppelin/contracts/access/Ownable.sol");
+                assert_eq!(imports[0].line, Some(2));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_solc_is_reported() {
+        let engine = DeployEngine::new("/nonexistent/solc".to_string(), "http://127.0.0.1:1".to_string());
+        let result = engine.compile_solidity("", "Missing.sol", "Missing", &resolver(Path::new(".")), &CompilerSettings::default());
+        assert!(matches!(result, Err(DeployError::SolcError(_))));
+    }
+}
@@ -0,0 +1,447 @@
+use chrono::Utc;
+use ethers::types::{Address, TxHash, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+use crate::api_error::ApiError;
+use crate::deploy_engine::{DeployEvent, FeeStrategy, LinkStep};
+use crate::solc_standard_json::{CompilerSettings, Diagnostic};
+
+/// Events buffered per subscriber; one that falls further behind misses events.
+const EVENT_BUFFER: usize = 256;
+
+/// Where a deployment job is. Jobs move through these in order, one transaction at a
+/// time from `Signing` to `Confirming`, and end in `Succeeded` or `Failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStage {
+    Queued,
+    Rendering,
+    Compiling,
+    Signing,
+    Broadcasting,
+    Confirming,
+    Succeeded,
+    Failed,
+}
+
+impl JobStage {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStage::Succeeded | JobStage::Failed)
+    }
+}
+
+/// The deploy request a job runs. KYC is checked before the job is created, so only
+/// the wallet it authorised is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRequest {
+    pub contract: String,
+    pub params: Value,
+    pub chain: String,
+    pub compiler_settings: CompilerSettings,
+    pub salt: Option<String>,
+    pub fee: FeeStrategy,
+    pub kyc_wallet: Option<Address>,
+}
+
+/// A transaction of the job, recorded before it is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobTransaction {
+    pub contract: String, // Fully qualified name of the library or main contract it creates
+    pub tx_hash: TxHash,
+    pub nonce: U256,
+    #[serde(serialize_with = "crate::address::serialize_checksummed")]
+    pub address: Address, // Where the contract will be once mined
+    pub block_number: Option<u64>, // Set once mined
+}
+
+/// The error body a failed job reports, as the synchronous API would have returned it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobError {
+    pub code: String,
+    pub message: String,
+    pub details: Value,
+}
+
+impl From<&ApiError> for JobError {
+    fn from(error: &ApiError) -> Self {
+        JobError { code: error.code().to_string(), message: error.to_string(), details: error.details() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageChange {
+    pub stage: JobStage,
+    pub at: i64,
+}
+
+/// A deployment run in the background, as returned by `GET /api/jobs/:id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployJob {
+    pub id: String,
+    pub stage: JobStage,
+    pub request: JobRequest,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub finished_at: Option<i64>,
+    pub history: Vec<StageChange>, // Every stage entered, with its time
+    pub main_contract: Option<String>, // Fully qualified, known once compiled
+    pub tx_hash: Option<TxHash>, // Of the latest transaction
+    pub transactions: Vec<JobTransaction>,
+    pub libraries: Vec<LinkStep>,
+    pub result: Option<Value>, // The deploy response, once succeeded
+    pub error: Option<JobError>,
+    #[serde(default)]
+    pub warnings: Vec<String>, // Problems that did not fail the job, e.g. an artifact left unstored
+}
+
+impl DeployJob {
+    fn new(id: String, request: JobRequest) -> Self {
+        let now = Utc::now().timestamp();
+        DeployJob {
+            id,
+            stage: JobStage::Queued,
+            request,
+            created_at: now,
+            updated_at: now,
+            finished_at: None,
+            history: vec![StageChange { stage: JobStage::Queued, at: now }],
+            main_contract: None,
+            tx_hash: None,
+            transactions: Vec::new(),
+            libraries: Vec::new(),
+            result: None,
+            error: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn set_stage(&mut self, stage: JobStage) {
+        let now = Utc::now().timestamp();
+        self.updated_at = now;
+        if stage != self.stage {
+            self.stage = stage;
+            self.history.push(StageChange { stage, at: now });
+        }
+        if stage.is_finished() {
+            self.finished_at = Some(now);
+        }
+    }
+
+    /// Records the progress reported by the deploy engine.
+    pub fn apply(&mut self, event: &DeployEvent) {
+        match event {
+            DeployEvent::Linked { step } => {
+                self.libraries.retain(|linked| linked.library != step.library);
+                self.libraries.push(step.clone());
+                self.updated_at = Utc::now().timestamp();
+            }
+            DeployEvent::Signing { .. } => self.set_stage(JobStage::Signing),
+            DeployEvent::Broadcasting { contract, tx_hash, nonce, address } => {
+                self.transactions.retain(|tx| tx.tx_hash != *tx_hash);
+                self.transactions.push(JobTransaction {
+                    contract: contract.clone(),
+                    tx_hash: *tx_hash,
+                    nonce: *nonce,
+                    address: *address,
+                    block_number: None,
+                });
+                self.tx_hash = Some(*tx_hash);
+                self.set_stage(JobStage::Broadcasting);
+            }
+            DeployEvent::Confirming { .. } => self.set_stage(JobStage::Confirming),
+            DeployEvent::Confirmation { tx_hash, block_number, .. } | DeployEvent::Confirmed { tx_hash, block_number, .. } => {
+                self.mined(*tx_hash, Some(*block_number));
+            }
+            DeployEvent::Reorged { tx_hash, .. } => self.mined(*tx_hash, None),
+            DeployEvent::Replaced { replaced, tx_hash, .. } => {
+                if let Some(tx) = self.transactions.iter().find(|tx| tx.tx_hash == *replaced) {
+                    let replacement = JobTransaction { tx_hash: *tx_hash, block_number: None, ..tx.clone() };
+                    self.transactions.push(replacement);
+                }
+                self.tx_hash = Some(*tx_hash);
+                self.updated_at = Utc::now().timestamp();
+            }
+        }
+    }
+
+    /// Records the block `tx_hash` is in, or `None` after a reorg.
+    fn mined(&mut self, tx_hash: TxHash, block_number: Option<u64>) {
+        if let Some(tx) = self.transactions.iter_mut().find(|tx| tx.tx_hash == tx_hash) {
+            tx.block_number = block_number;
+        }
+        self.updated_at = Utc::now().timestamp();
+    }
+
+    pub fn succeed(&mut self, result: Value) {
+        self.result = Some(result);
+        self.set_stage(JobStage::Succeeded);
+    }
+
+    /// Records a problem that does not fail the job.
+    pub fn warn(&mut self, message: String) {
+        self.warnings.push(message);
+        self.updated_at = Utc::now().timestamp();
+    }
+
+    pub fn fail(&mut self, error: JobError) {
+        self.error = Some(error);
+        self.set_stage(JobStage::Failed);
+    }
+
+    /// Whether the job sent a transaction, so running it again could deploy twice.
+    pub fn has_broadcast(&self) -> bool {
+        !self.transactions.is_empty()
+    }
+}
+
+/// Progress of a job, as streamed by `GET /api/jobs/:id/events`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    Stage { stage: JobStage },
+    Compiled { contract: String, warnings: Vec<Diagnostic> },
+    ArtifactStored {
+        contract: String, // Fully qualified
+        #[serde(serialize_with = "crate::address::serialize_checksummed")]
+        address: Address,
+        path: PathBuf,
+    },
+    Warning { message: String },
+    Finished { job: Box<DeployJob> }, // Last event of a job, whether it succeeded or failed
+    #[serde(untagged)]
+    Deploy(DeployEvent), // Already tagged with its own `event`
+}
+
+#[derive(Debug, Clone)]
+pub struct JobUpdate {
+    pub job_id: String,
+    pub event: JobEvent,
+}
+
+/// Deployment jobs, kept in memory and persisted as one JSON file per job so that they
+/// survive a restart.
+pub struct JobStore {
+    dir: PathBuf,
+    jobs: Mutex<HashMap<String, DeployJob>>,
+    events: broadcast::Sender<JobUpdate>,
+}
+
+impl JobStore {
+    /// Opens `dir`, loading every job persisted there. Unreadable files are skipped.
+    pub fn new(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let mut jobs = HashMap::new();
+        for entry in fs::read_dir(&dir)?.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match fs::read(&path).map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_slice::<DeployJob>(&raw).map_err(|e| e.to_string()))
+            {
+                Ok(job) => {
+                    jobs.insert(job.id.clone(), job);
+                }
+                Err(e) => eprintln!("Skipping unreadable job file {}: {}", path.display(), e),
+            }
+        }
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Ok(Self { dir, jobs: Mutex::new(jobs), events })
+    }
+
+    /// Creates and persists a queued job for `request`.
+    pub fn create(&self, request: JobRequest) -> DeployJob {
+        let id = hex::encode(ethers::core::rand::random::<[u8; 16]>());
+        let job = DeployJob::new(id.clone(), request);
+        self.persist(&job);
+        self.jobs.lock().unwrap().insert(id, job.clone());
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<DeployJob> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Applies `change` to job `id` and persists the result. A change of stage is
+    /// published, followed by the whole job once it finishes.
+    pub fn update(&self, id: &str, change: impl FnOnce(&mut DeployJob)) -> Option<DeployJob> {
+        let (previous, job) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs.get_mut(id)?;
+            let previous = job.stage;
+            change(job);
+            (previous, job.clone())
+        };
+        self.persist(&job);
+        if job.stage != previous {
+            self.publish(id, JobEvent::Stage { stage: job.stage });
+            if job.stage.is_finished() {
+                self.publish(id, JobEvent::Finished { job: Box::new(job.clone()) });
+            }
+        }
+        Some(job)
+    }
+
+    /// Sends `event` to the subscribers of job `id`, if there are any.
+    pub fn publish(&self, id: &str, event: JobEvent) {
+        let _ = self.events.send(JobUpdate { job_id: id.to_string(), event });
+    }
+
+    /// Receives the events of every job from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<JobUpdate> {
+        self.events.subscribe()
+    }
+
+    /// Jobs that neither succeeded nor failed, oldest first.
+    pub fn unfinished(&self) -> Vec<DeployJob> {
+        let mut jobs: Vec<DeployJob> = self.jobs.lock().unwrap().values()
+            .filter(|job| !job.stage.is_finished())
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| job.created_at);
+        jobs
+    }
+
+    /// Writes `job` through a temporary file, so a crash never leaves a partial one.
+    /// Failures are logged; the job carries on in memory.
+    fn persist(&self, job: &DeployJob) {
+        let path = self.dir.join(format!("{}.json", job.id));
+        let tmp_path = self.dir.join(format!("{}.tmp", job.id));
+        let written = serde_json::to_vec_pretty(job)
+            .map_err(std::io::Error::from)
+            .and_then(|json| fs::write(&tmp_path, json))
+            .and_then(|()| fs::rename(&tmp_path, &path));
+        if let Err(e) = written {
+            eprintln!("Could not persist job {} to {}: {}", job.id, path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> JobRequest {
+        JobRequest {
+            contract: "TokenVesting.sol.tera".to_string(),
+            params: serde_json::json!({ "duration": 10 }),
+            chain: "local".to_string(),
+            compiler_settings: CompilerSettings::default(),
+            salt: None,
+            fee: FeeStrategy::Normal,
+            kyc_wallet: None,
+        }
+    }
+
+    #[test]
+    fn test_events_move_the_job_forward() {
+        let mut job = DeployJob::new("a".to_string(), request());
+        let tx_hash = TxHash::repeat_byte(1);
+        job.set_stage(JobStage::Compiling);
+        job.apply(&DeployEvent::Signing { contract: "Vault.sol:Vault".to_string() });
+        job.apply(&DeployEvent::Broadcasting {
+            contract: "Vault.sol:Vault".to_string(),
+            tx_hash,
+            nonce: U256::from(7),
+            address: Address::repeat_byte(2),
+        });
+        assert_eq!((job.stage, job.tx_hash), (JobStage::Broadcasting, Some(tx_hash)));
+        assert!(job.has_broadcast());
+        job.apply(&DeployEvent::Confirming { contract: "Vault.sol:Vault".to_string(), tx_hash });
+        let replacement = TxHash::repeat_byte(3);
+        job.apply(&DeployEvent::Replaced {
+            contract: "Vault.sol:Vault".to_string(),
+            replaced: tx_hash,
+            tx_hash: replacement,
+            nonce: U256::from(7),
+        });
+        assert_eq!(job.transactions.len(), 2);
+        assert_eq!((job.transactions[1].nonce, job.tx_hash), (U256::from(7), Some(replacement)));
+        job.apply(&DeployEvent::Confirmation {
+            contract: "Vault.sol:Vault".to_string(),
+            tx_hash: replacement,
+            block_number: 3,
+            confirmations: 1,
+            required: 2,
+        });
+        job.apply(&DeployEvent::Reorged { contract: "Vault.sol:Vault".to_string(), tx_hash: replacement, block_number: 3 });
+        assert_eq!(job.transactions[1].block_number, None);
+        job.apply(&DeployEvent::Confirmed { contract: "Vault.sol:Vault".to_string(), tx_hash: replacement, block_number: 4 });
+        assert_eq!(job.transactions[1].block_number, Some(4));
+
+        job.succeed(serde_json::json!({ "address": "0x02" }));
+        assert!(job.finished_at.is_some());
+        let stages: Vec<_> = job.history.iter().map(|change| change.stage).collect();
+        assert_eq!(stages, [
+            JobStage::Queued,
+            JobStage::Compiling,
+            JobStage::Signing,
+            JobStage::Broadcasting,
+            JobStage::Confirming,
+            JobStage::Succeeded,
+        ]);
+    }
+
+    #[test]
+    fn test_jobs_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().to_path_buf()).unwrap();
+        let running = store.create(request());
+        let done = store.create(request());
+        store.update(&running.id, |job| job.set_stage(JobStage::Confirming));
+        store.update(&running.id, |job| job.warn("artifact not stored".to_string()));
+        store.update(&done.id, |job| job.fail(JobError {
+            code: "rpc_error".to_string(),
+            message: "timeout".to_string(),
+            details: Value::Null,
+        }));
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        // Jobs persisted before warnings were recorded still load.
+        let mut legacy = serde_json::to_value(DeployJob::new("legacy".to_string(), request())).unwrap();
+        legacy.as_object_mut().unwrap().remove("warnings");
+        fs::write(dir.path().join("legacy.json"), legacy.to_string()).unwrap();
+
+        let reopened = JobStore::new(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reopened.get(&done.id).unwrap().error.unwrap().code, "rpc_error");
+        assert!(reopened.get("legacy").unwrap().warnings.is_empty());
+        let unfinished: Vec<_> = reopened.unfinished().into_iter().filter(|job| job.id != "legacy").collect();
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!((unfinished[0].id.as_str(), unfinished[0].stage), (running.id.as_str(), JobStage::Confirming));
+        assert_eq!(unfinished[0].warnings, ["artifact not stored"]);
+        assert!(reopened.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_subscribers_receive_job_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().to_path_buf()).unwrap();
+        let job = store.create(request());
+        let mut events = store.subscribe();
+
+        store.update(&job.id, |job| job.set_stage(JobStage::Rendering));
+        store.update(&job.id, |job| job.set_stage(JobStage::Rendering));
+        store.publish(&job.id, JobEvent::Deploy(DeployEvent::Confirming {
+            contract: "Vault.sol:Vault".to_string(),
+            tx_hash: TxHash::repeat_byte(1),
+        }));
+        store.update(&job.id, |job| job.succeed(serde_json::json!({})));
+
+        let received: Vec<Value> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|update| {
+                assert_eq!(update.job_id, job.id);
+                serde_json::to_value(update.event).unwrap()
+            })
+            .collect();
+        let names: Vec<_> = received.iter().map(|event| event["event"].as_str().unwrap()).collect();
+        assert_eq!(names, ["stage", "confirming", "stage", "finished"]);
+        assert_eq!(received[0]["stage"], "rendering");
+        assert_eq!(received[1]["contract"], "Vault.sol:Vault");
+        assert_eq!(received[3]["job"]["stage"], "succeeded");
+    }
+}
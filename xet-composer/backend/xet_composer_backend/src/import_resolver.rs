@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use solang_parser::helpers::CodeLocation;
+use solang_parser::pt::{Loc, SourceUnitPart};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::solc_standard_json::line_and_column;
+
+/// A directory of Solidity sources imported under a prefix, e.g. OpenZeppelin's
+/// `contracts/` directory under `@openzeppelin/contracts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LibraryRoot {
+    /// Import prefix, without a trailing slash.
+    pub name: String,
+    /// Relative paths are resolved against the contracts directory.
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl LibraryRoot {
+    /// Parses the `--library` flag syntax, `NAME[@VERSION]=PATH`, e.g.
+    /// `@openzeppelin/contracts@4.9.3=lib/openzeppelin-repo/contracts`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (lhs, path) = spec.split_once('=')
+            .ok_or_else(|| format!("library '{}' must have the form NAME[@VERSION]=PATH", spec))?;
+        // A leading `@` belongs to a scoped name, not to a version.
+        let (name, version) = match lhs.rsplit_once('@') {
+            Some((name, version)) if !name.is_empty() => (name, Some(version.to_string())),
+            _ => (lhs, None),
+        };
+        Ok(Self { name: name.to_string(), path: PathBuf::from(path), version })
+    }
+}
+
+/// An `import` in the rendered source that does not resolve to a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedImport {
+    pub path: String,
+    /// 1-based position of the import directive in the rendered source.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Where the import was looked for.
+    pub searched: PathBuf,
+    /// The library root whose prefix matched, if any, e.g. `@openzeppelin/contracts@4.9.3`.
+    pub library: Option<String>,
+}
+
+/// Resolves imports the way solc does with `--base-path` and remappings, and produces
+/// the remappings and `--allow-paths` that make solc find library sources.
+#[derive(Debug, Clone)]
+pub struct ImportResolver {
+    base_path: PathBuf,
+    libraries: Vec<LibraryRoot>, // Paths made absolute
+    extra_remappings: Vec<String>,
+}
+
+impl ImportResolver {
+    pub fn new(base_path: PathBuf, libraries: &[LibraryRoot], extra_remappings: Vec<String>) -> Self {
+        let libraries = libraries.iter().map(|library| LibraryRoot {
+            name: library.name.trim_end_matches('/').to_string(),
+            path: base_path.join(&library.path),
+            version: library.version.clone(),
+        }).collect();
+        Self { base_path, libraries, extra_remappings }
+    }
+
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// One `name/=path/` remapping per library root, followed by the configured extras.
+    pub fn remappings(&self) -> Vec<String> {
+        self.libraries.iter()
+            .map(|library| format!("{}/={}/", library.name, library.path.display()))
+            .chain(self.extra_remappings.iter().cloned())
+            .collect()
+    }
+
+    /// Library directories solc must be allowed to read besides the base path.
+    pub fn allow_paths(&self) -> Vec<PathBuf> {
+        self.libraries.iter().map(|library| library.path.clone()).collect()
+    }
+
+    /// Checks that every import of `source`, the source unit `source_name`, resolves to a
+    /// file. Sources solang-parser cannot read are let through for solc to report on.
+    pub fn check_imports(&self, source: &str, source_name: &str) -> Result<(), Vec<UnresolvedImport>> {
+        let unresolved: Vec<UnresolvedImport> = imports(source).into_iter()
+            .filter_map(|(import_path, offset)| {
+                let (searched, library) = self.resolve(&import_path, source_name);
+                if searched.is_file() {
+                    return None;
+                }
+                let (line, column) = offset.and_then(|offset| line_and_column(source, offset)).unzip();
+                Some(UnresolvedImport { path: import_path, line, column, searched, library })
+            })
+            .collect();
+        if unresolved.is_empty() { Ok(()) } else { Err(unresolved) }
+    }
+
+    /// Every file `source` imports, directly or through the files it imports, keyed by
+    /// the path it is read from, with its contents. Imports that do not resolve are left
+    /// out, as are the imports of files solang-parser cannot read.
+    pub fn imported_files(&self, source: &str, source_name: &str) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        let mut pending: Vec<(String, String)> = imports(source).into_iter()
+            .map(|(import_path, _)| (import_path, source_name.to_string()))
+            .collect();
+        while let Some((import_path, importer)) = pending.pop() {
+            let unit_name = unit_name(&import_path, &importer);
+            let (path, _) = self.resolve(&unit_name, &unit_name);
+            if files.contains_key(&path) {
+                continue;
+            }
+            let Ok(contents) = std::fs::read(&path) else {
+                continue;
+            };
+            pending.extend(imports(&String::from_utf8_lossy(&contents)).into_iter()
+                .map(|(import_path, _)| (import_path, unit_name.clone())));
+            files.insert(path, contents);
+        }
+        files
+    }
+
+    /// The file `import_path` refers to from source unit `importer`, and the library whose
+    /// prefix matched. Relative imports are resolved against the importer's directory first;
+    /// the longest matching remapping then applies, and the result is read from the base path.
+    fn resolve(&self, import_path: &str, importer: &str) -> (PathBuf, Option<String>) {
+        let unit_name = unit_name(import_path, importer);
+
+        // Library roots act as remappings; the longest matching prefix wins.
+        let best = self.libraries.iter()
+            .map(|library| {
+                let label = match &library.version {
+                    Some(version) => format!("{}@{}", library.name, version),
+                    None => library.name.clone(),
+                };
+                (format!("{}/", library.name), library.path.clone(), Some(label))
+            })
+            .chain(self.extra_remappings.iter()
+                .filter_map(|remapping| remapping.split_once('='))
+                .map(|(prefix, target)| (prefix.to_string(), self.base_path.join(target), None)))
+            .filter(|(prefix, _, _)| unit_name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _, _)| prefix.len());
+
+        match best {
+            Some((prefix, target, library)) => (target.join(&unit_name[prefix.len()..]), library),
+            None => (self.base_path.join(&unit_name), None),
+        }
+    }
+}
+
+/// The import paths of `source` with the offset of their directive. Sources
+/// solang-parser cannot read have none.
+fn imports(source: &str) -> Vec<(String, Option<usize>)> {
+    let Ok((unit, _comments)) = solang_parser::parse(source, 0) else {
+        return Vec::new();
+    };
+    unit.0.iter()
+        .filter_map(|part| match part {
+            SourceUnitPart::ImportDirective(import) => {
+                let offset = match part.loc() {
+                    Loc::File(_, start, _) => Some(start),
+                    _ => None,
+                };
+                Some((import.literal()?.string.clone(), offset))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The source unit name `import_path` stands for in source unit `importer`: relative
+/// imports are taken from the importer's directory, others are names already.
+fn unit_name(import_path: &str, importer: &str) -> String {
+    if import_path.starts_with("./") || import_path.starts_with("../") {
+        let importer_dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        normalize(&importer_dir.join(import_path))
+    } else {
+        import_path.to_string()
+    }
+}
+
+/// Collapses `.` and `..` segments of a relative source unit name.
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part.to_str().unwrap_or_default()),
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn resolver(dir: &Path) -> ImportResolver {
+        fs::create_dir_all(dir.join("lib/oz/contracts/access")).unwrap();
+        fs::write(dir.join("lib/oz/contracts/access/Ownable.sol"), "").unwrap();
+        fs::write(dir.join("Local.sol"), "").unwrap();
+        let oz = LibraryRoot::parse("@openzeppelin/contracts@4.9.3=lib/oz/contracts").unwrap();
+        ImportResolver::new(dir.to_path_buf(), &[oz], Vec::new())
+    }
+
+    #[test]
+    fn test_library_spec() {
+        let library = LibraryRoot::parse("@openzeppelin/contracts@4.9.3=lib/oz").unwrap();
+        assert_eq!((library.name.as_str(), library.version.as_deref()), ("@openzeppelin/contracts", Some("4.9.3")));
+        let library = LibraryRoot::parse("@openzeppelin/contracts=lib/oz").unwrap();
+        assert_eq!((library.name.as_str(), library.version), ("@openzeppelin/contracts", None));
+        assert!(LibraryRoot::parse("@openzeppelin/contracts").is_err());
+    }
+
+    #[test]
+    fn test_remappings_and_allow_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolver = resolver(dir.path());
+        let oz = dir.path().join("lib/oz/contracts");
+        assert_eq!(resolver.remappings(), vec![format!("@openzeppelin/contracts/={}/", oz.display())]);
+        assert_eq!(resolver.allow_paths(), vec![oz]);
+    }
+
+    #[test]
+    fn test_unresolved_imports_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolver = resolver(dir.path());
+        let source = "pragma solidity ^0.8.19;\n\
+            import \"@openzeppelin/contracts/access/Ownable.sol\";\n\
+            import {Missing} from \"@openzeppelin/contracts/token/Missing.sol\";\n\
+            import \"./Local.sol\";\n\
+            import \"../Outside.sol\";\n\
+            contract A {}\n";
+        let unresolved = resolver.check_imports(source, "A.sol").unwrap_err();
+        assert_eq!(unresolved.len(), 2);
+        assert_eq!(unresolved[0].path, "@openzeppelin/contracts/token/Missing.sol");
+        assert_eq!((unresolved[0].line, unresolved[0].column), (Some(3), Some(1)));
+        assert_eq!(unresolved[0].library.as_deref(), Some("@openzeppelin/contracts@4.9.3"));
+        assert_eq!(unresolved[1].path, "../Outside.sol");
+        assert_eq!(unresolved[1].library, None);
+
+        assert!(resolver.check_imports("import \"./Local.sol\";\ncontract A {}\n", "A.sol").is_ok());
+    }
+
+    #[test]
+    fn test_imported_files_follow_nested_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolver = resolver(dir.path());
+        let oz = dir.path().join("lib/oz/contracts");
+        fs::create_dir_all(oz.join("utils")).unwrap();
+        fs::write(oz.join("access/Ownable.sol"), "import \"../utils/Context.sol\";\ncontract Ownable {}\n").unwrap();
+        fs::write(oz.join("utils/Context.sol"), "import \"./Missing.sol\";\ncontract Context {}\n").unwrap();
+        let source = "import \"@openzeppelin/contracts/access/Ownable.sol\";\nimport \"./Local.sol\";\ncontract A {}\n";
+
+        let files = resolver.imported_files(source, "A.sol");
+        let paths: Vec<_> = files.keys().cloned().collect();
+        assert_eq!(paths, [dir.path().join("Local.sol"), oz.join("access/Ownable.sol"), oz.join("utils/Context.sol")]);
+        assert_eq!(files[&oz.join("utils/Context.sol")], b"import \"./Missing.sol\";\ncontract Context {}\n");
+    }
+}
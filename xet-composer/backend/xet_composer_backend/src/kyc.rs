@@ -1,5 +1,176 @@
 // src/kyc.rs
 
+use chrono::Utc;
+use ethers::types::{Address, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use crate::address::parse_address;
+
+/// How long an issued nonce can be used for, in seconds.
+pub const NONCE_TTL_SECS: i64 = 300;
+
+/// Unused nonces one wallet can hold at once.
+pub const MAX_NONCES_PER_WALLET: usize = 5;
+
+/// Unused nonces held across every wallet.
+pub const MAX_NONCES: usize = 10_000;
+
+/// KYC block carried by a deploy request.
+#[derive(Deserialize, Debug, Clone)]
+pub struct KycPayload {
+    pub legal_name: String,
+    pub wallet_address: String,
+    /// Nonce previously issued by `/api/kyc/nonce` for this wallet.
+    pub nonce: String,
+    /// EIP-191 `personal_sign` signature over [`kyc_message`].
+    pub signature: String,
+}
+
+/// A nonce handed out to a wallet, returned by the nonce endpoint.
+#[derive(Serialize, Debug, Clone)]
+pub struct IssuedNonce {
+    pub nonce: String,
+    pub wallet_address: Address,
+    /// The exact text the wallet must sign.
+    pub message: String,
+    pub expires_at: i64,
+}
+
+/// The message a wallet signs to prove control of `wallet_address`.
+pub fn kyc_message(wallet_address: &Address, nonce: &str) -> String {
+    format!(
+        "XET Composer KYC verification\nWallet: {:?}\nNonce: {}",
+        wallet_address, nonce
+    )
+}
+
+/// Single-use nonces, kept in memory until they are consumed or expire. Anyone can ask
+/// for one, so the unused nonces are capped per wallet and in total.
+pub struct NonceStore {
+    nonces: Mutex<Nonces>,
+    max_per_wallet: usize,
+    max_total: usize,
+}
+
+#[derive(Default)]
+struct Nonces {
+    issued: HashMap<String, IssuedNonce>,
+    per_wallet: HashMap<Address, usize>,
+    by_expiry: BTreeSet<(i64, String)>, // Soonest to expire first
+}
+
+impl Nonces {
+    fn remove(&mut self, nonce: &str) -> Option<IssuedNonce> {
+        let issued = self.issued.remove(nonce)?;
+        self.by_expiry.remove(&(issued.expires_at, issued.nonce.clone()));
+        if let Some(count) = self.per_wallet.get_mut(&issued.wallet_address) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_wallet.remove(&issued.wallet_address);
+            }
+        }
+        Some(issued)
+    }
+
+    /// Drops the nonces that expired by `now`, soonest first, so only those are visited.
+    fn prune(&mut self, now: i64) {
+        while let Some((expires_at, nonce)) = self.by_expiry.first().cloned() {
+            if expires_at > now {
+                break;
+            }
+            self.by_expiry.pop_first();
+            self.remove(&nonce);
+        }
+    }
+}
+
+impl Default for NonceStore {
+    fn default() -> Self {
+        Self::with_limits(MAX_NONCES_PER_WALLET, MAX_NONCES)
+    }
+}
+
+impl NonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store holding at most `max_per_wallet` unused nonces for a wallet, and
+    /// `max_total` overall.
+    pub fn with_limits(max_per_wallet: usize, max_total: usize) -> Self {
+        Self { nonces: Mutex::default(), max_per_wallet, max_total }
+    }
+
+    /// Issues a fresh nonce for `wallet_address`, dropping expired ones on the way.
+    /// Fails once the wallet, or the store, holds as many unused nonces as allowed.
+    pub fn issue(&self, wallet_address: Address) -> Result<IssuedNonce, String> {
+        let now = Utc::now().timestamp();
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.prune(now);
+        if nonces.per_wallet.get(&wallet_address).copied().unwrap_or_default() >= self.max_per_wallet {
+            return Err(format!(
+                "{:?} already holds {} unused nonces; use one or wait for them to expire.",
+                wallet_address, self.max_per_wallet
+            ));
+        }
+        if nonces.issued.len() >= self.max_total {
+            return Err("Too many unused nonces are outstanding; try again later.".to_string());
+        }
+
+        let nonce = hex::encode(ethers::core::rand::random::<[u8; 16]>());
+        let issued = IssuedNonce {
+            message: kyc_message(&wallet_address, &nonce),
+            nonce: nonce.clone(),
+            wallet_address,
+            expires_at: now + NONCE_TTL_SECS,
+        };
+        *nonces.per_wallet.entry(wallet_address).or_default() += 1;
+        nonces.by_expiry.insert((issued.expires_at, nonce.clone()));
+        nonces.issued.insert(nonce, issued.clone());
+        Ok(issued)
+    }
+
+    /// Verifies `payload` and consumes its nonce, so the same signature cannot be replayed.
+    /// The nonce is only consumed once the signature checks out.
+    pub fn verify(&self, payload: &KycPayload) -> Result<Address, String> {
+        simulate_kyc_validation(&payload.legal_name, &payload.wallet_address, &payload.signature)?;
+        let claimed = parse_address(&payload.wallet_address, false)
+            .map_err(|e| format!("Invalid wallet address: {}.", e))?;
+
+        let mut nonces = self.nonces.lock().unwrap();
+        let issued = nonces.issued.get(&payload.nonce)
+            .ok_or_else(|| "Unknown or already used nonce.".to_string())?;
+        if issued.expires_at <= Utc::now().timestamp() {
+            nonces.remove(&payload.nonce);
+            return Err("Nonce has expired.".to_string());
+        }
+        if issued.wallet_address != claimed {
+            return Err("Nonce was issued for a different wallet.".to_string());
+        }
+
+        let recovered = recover_signer(&issued.message, &payload.signature)?;
+        if recovered != claimed {
+            return Err(format!(
+                "Signature was produced by {:?}, not the claimed wallet {:?}.",
+                recovered, claimed
+            ));
+        }
+
+        nonces.remove(&payload.nonce);
+        Ok(claimed)
+    }
+}
+
+/// Recovers the address that `personal_sign`ed `message`.
+pub fn recover_signer(message: &str, signature: &str) -> Result<Address, String> {
+    let signature: Signature = signature.trim_start_matches("0x").parse()
+        .map_err(|e| format!("Invalid signature: {}", e))?;
+    signature.recover(message)
+        .map_err(|e| format!("Could not recover signer: {}", e))
+}
+
 /// Simulates a basic KYC validation check.
 ///
 /// # Arguments
@@ -27,13 +198,9 @@ pub fn simulate_kyc_validation(
         return Err("Signature or hash cannot be empty.".to_string());
     }
 
-    // Basic wallet address format check (e.g., Ethereum address)
-    if !wallet_address.starts_with("0x") || wallet_address.len() != 42 {
-        return Err("Invalid wallet address format. Expected '0x' prefix and 42 characters total.".to_string());
-    }
-
-    // Optionally, add more sophisticated checks for wallet_address characters (e.g., hex)
-    // For now, the prefix and length check is sufficient as per requirements.
+    // Hex, length and EIP-55 checksum check; the zero address can never sign.
+    parse_address(wallet_address, false)
+        .map_err(|e| format!("Invalid wallet address: {}.", e))?;
 
     Ok(())
 }
@@ -42,6 +209,7 @@ pub fn simulate_kyc_validation(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ethers::signers::{LocalWallet, Signer};
 
     #[test]
     fn test_kyc_validation_success() {
@@ -95,7 +263,7 @@ mod tests {
                 "1x1234567890abcdef1234567890abcdef12345678", // Invalid prefix
                 "some_signature_hash"
             ),
-            Err("Invalid wallet address format. Expected '0x' prefix and 42 characters total.".to_string())
+            Err("Invalid wallet address: missing '0x' prefix.".to_string())
         );
     }
 
@@ -107,7 +275,7 @@ mod tests {
                 "0x12345", // Too short
                 "some_signature_hash"
             ),
-            Err("Invalid wallet address format. Expected '0x' prefix and 42 characters total.".to_string())
+            Err("Invalid wallet address: expected 40 hex digits after '0x', found 5.".to_string())
         );
     }
 
@@ -119,7 +287,103 @@ mod tests {
                 "0x1234567890abcdef1234567890abcdef1234567890ab", // Too long
                 "some_signature_hash"
             ),
-            Err("Invalid wallet address format. Expected '0x' prefix and 42 characters total.".to_string())
+            Err("Invalid wallet address: expected 40 hex digits after '0x', found 44.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_kyc_invalid_wallet_address_characters() {
+        assert_eq!(
+            simulate_kyc_validation(
+                "Grace Hopper",
+                "0xZZZZ567890abcdef1234567890abcdef12345678", // Right length, not hex
+                "some_signature_hash"
+            ),
+            Err("Invalid wallet address: non-hex character 'Z' at position 2.".to_string())
         );
     }
+
+    #[test]
+    fn test_kyc_invalid_wallet_address_checksum() {
+        assert_eq!(
+            simulate_kyc_validation(
+                "Heidi Lamarr",
+                "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", // Mixed case, wrong checksum
+                "some_signature_hash"
+            ),
+            Err("Invalid wallet address: EIP-55 checksum mismatch, expected 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed.".to_string())
+        );
+    }
+
+    async fn signed_payload(store: &NonceStore, wallet: &LocalWallet, claimed: Address) -> KycPayload {
+        let issued = store.issue(claimed).unwrap();
+        let signature = wallet.sign_message(&issued.message).await.unwrap();
+        KycPayload {
+            legal_name: "Alice Wonderland".to_string(),
+            wallet_address: format!("{:?}", claimed),
+            nonce: issued.nonce,
+            signature: format!("0x{}", signature),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kyc_signature_verification() {
+        let store = NonceStore::new();
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let payload = signed_payload(&store, &wallet, wallet.address()).await;
+        assert_eq!(store.verify(&payload), Ok(wallet.address()));
+    }
+
+    #[tokio::test]
+    async fn test_kyc_nonce_cannot_be_replayed() {
+        let store = NonceStore::new();
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let payload = signed_payload(&store, &wallet, wallet.address()).await;
+        assert!(store.verify(&payload).is_ok());
+        assert_eq!(store.verify(&payload), Err("Unknown or already used nonce.".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_kyc_rejects_wrong_signer() {
+        let store = NonceStore::new();
+        let signer = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let claimed = LocalWallet::new(&mut ethers::core::rand::thread_rng()).address();
+        let payload = signed_payload(&store, &signer, claimed).await;
+        let err = store.verify(&payload).unwrap_err();
+        assert!(err.starts_with("Signature was produced by"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_kyc_rejects_expired_nonce() {
+        let store = NonceStore::new();
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let payload = signed_payload(&store, &wallet, wallet.address()).await;
+        store.nonces.lock().unwrap().issued.get_mut(&payload.nonce).unwrap().expires_at = 0;
+        assert_eq!(store.verify(&payload), Err("Nonce has expired.".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_kyc_nonces_are_capped() {
+        let store = NonceStore::with_limits(2, 3);
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let payload = signed_payload(&store, &wallet, wallet.address()).await;
+        store.issue(wallet.address()).unwrap();
+        let err = store.issue(wallet.address()).unwrap_err();
+        assert!(err.contains("already holds 2 unused nonces"), "{}", err);
+
+        // Using a nonce frees its slot.
+        assert!(store.verify(&payload).is_ok());
+        store.issue(wallet.address()).unwrap();
+        store.issue(Address::repeat_byte(1)).unwrap();
+        let err = store.issue(Address::repeat_byte(2)).unwrap_err();
+        assert!(err.starts_with("Too many unused nonces"), "{}", err);
+
+        // Expired nonces free theirs; backdate every one.
+        let mut nonces = store.nonces.lock().unwrap();
+        let expired: Vec<_> = std::mem::take(&mut nonces.by_expiry).into_iter().map(|(_, nonce)| (0, nonce)).collect();
+        nonces.by_expiry.extend(expired);
+        drop(nonces);
+        store.issue(Address::repeat_byte(2)).unwrap();
+        assert_eq!(store.nonces.lock().unwrap().issued.len(), 1);
+    }
 }
@@ -1,27 +1,192 @@
-use axum::{routing::post, Router, Json};
+use axum::{extract::{rejection::JsonRejection, Path, State}, http::StatusCode, routing::{get, post}, Router, Json};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
 use std::env; // Added for env::var
+use std::path::PathBuf;
+use std::sync::Arc;
 
 // Existing module declarations
 mod sol_template_engine;
 mod deploy_engine;
 mod artifact_storage; // Added new module
 mod kyc; // Added KYC module
+mod template_schema;
+mod api_error;
+mod solc_standard_json;
+mod address;
+mod abi_value;
+mod config;
+mod template_store;
+mod solc_versions;
+mod compile_cache;
+mod import_resolver;
+mod deploy_jobs;
+mod nonce_manager;
 
 // Use statements for our modules
-use sol_template_engine::{SolTemplateEngine, TemplateError};
-use deploy_engine::{DeployEngine, CompiledArtifact, DeployError}; // Changed CompilationOutput to CompiledArtifact
-use crate::artifact_storage::store_artifact; // Added for storing artifacts
-use crate::kyc::simulate_kyc_validation; // Added for KYC
+use sol_template_engine::{TemplateDetail, TemplateInfo};
+use deploy_engine::{
+    parse_salt, AddressPrediction, Compilation, CompiledArtifact, Create2Info, DeployEngine, DeployError, DeploymentResult,
+    DeployOptions, DeployEvent, FeeStrategy, GasEstimate, LibrarySource, LinkStep, SignerSource,
+};
+use crate::artifact_storage::{find_deployed, store_artifact}; // Added for storing artifacts
+use crate::kyc::{IssuedNonce, KycPayload, NonceStore}; // Added for KYC
+use crate::api_error::ApiError;
+use crate::solc_standard_json::{CompilerSettings, Diagnostic, BytecodeRange};
+use crate::address::{checksum, parse_address};
+use crate::config::{AppConfig, CliArgs, KycMode};
+use crate::template_store::{ReloadStatus, TemplateStore};
+use crate::solc_versions::{CompilerCache, InstalledCompiler};
+use crate::compile_cache::{CacheStats, CompileCache};
+use crate::import_resolver::ImportResolver;
+use crate::deploy_jobs::{DeployJob, JobError, JobEvent, JobRequest, JobStage, JobStore};
+use crate::nonce_manager::NonceManager;
 use chrono::Utc; // Added for timestamp
-use serde::Serialize; // Ensure Serialize is in scope for FrontendDeployResponse
+use clap::Parser;
+use ethers::abi::Abi;
+use ethers::signers::Signer;
+use ethers::types::{Address, U256};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// State shared by all handlers.
+#[derive(Clone)]
+struct AppState {
+    config: Arc<AppConfig>,
+    kyc_nonces: Arc<NonceStore>,
+    templates: Arc<TemplateStore>,
+    deploy_engines: Arc<HashMap<String, DeployEngine>>, // Keyed by chain name
+    compilers: Option<Arc<CompilerCache>>,
+    compile_cache: Option<Arc<CompileCache>>,
+    imports: Arc<ImportResolver>,
+    jobs: Arc<JobStore>,
+}
 
 #[derive(Deserialize, Debug)]
 struct DeployRequest {
     contract: String, // e.g., "TokenVesting.sol.tera"
     params: serde_json::Value,
+    #[serde(default)]
+    chain: Option<String>, // Key of `[chains]` in the config; the default chain when absent
+    #[serde(default)]
+    kyc: Option<KycPayload>, // Required unless KYC is disabled in the config
+    #[serde(default)]
+    compiler_settings: CompilerSettings, // Overrides the template manifest's settings
+    #[serde(default)]
+    salt: Option<String>, // Deploys through the CREATE2 factory when set; see `parse_salt`
+    #[serde(default)]
+    fee: FeeStrategy,
+}
+
+/// Body of `/api/estimate`: a deploy request without KYC.
+#[derive(Deserialize, Debug)]
+struct EstimateRequest {
+    contract: String,
+    params: serde_json::Value,
+    #[serde(default)]
+    chain: Option<String>,
+    #[serde(default)]
+    compiler_settings: CompilerSettings,
+    #[serde(default)]
+    salt: Option<String>,
+    #[serde(default)]
+    fee: FeeStrategy,
+}
+
+/// Body of `/api/predict-address`: a deploy request without KYC, with a mandatory salt.
+#[derive(Deserialize, Debug)]
+struct PredictRequest {
+    contract: String,
+    params: serde_json::Value,
+    #[serde(default)]
+    chain: Option<String>,
+    #[serde(default)]
+    compiler_settings: CompilerSettings,
+    salt: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct NonceRequest {
+    wallet_address: String,
+}
+
+/// Body of the dry-run endpoints, `/api/render` and `/api/compile`.
+#[derive(Deserialize, Debug)]
+struct RenderRequest {
+    contract: String, // e.g., "TokenVesting.sol.tera"
+    #[serde(default)]
+    params: serde_json::Value,
+    #[serde(default)]
+    compiler_settings: CompilerSettings, // Only used by `/api/compile`
+}
+
+#[derive(Serialize, Debug)]
+struct RenderResponse {
+    contract: String,
+    source: String,
+}
+
+#[derive(Serialize, Debug)]
+struct CompileResponse {
+    contract: String,
+    abi: Abi,
+    bytecode: String,
+    deployed_bytecode: String,
+    bytecode_size: usize, // Creation bytecode, in bytes
+    deployed_bytecode_size: usize, // Runtime bytecode, in bytes; limited to 24576 by EIP-170
+    metadata: String,
+    storage_layout: serde_json::Value,
+    method_identifiers: BTreeMap<String, String>,
+    immutable_references: BTreeMap<String, Vec<BytecodeRange>>,
+    source_map: String,
+    deployed_source_map: String,
+    compiler_version: Option<String>,
+    libraries: Vec<CompiledArtifact>, // Libraries the template defines or imports
+    helpers: Vec<CompiledArtifact>, // Every other contract and interface of the compilation
+    diagnostics: Vec<Diagnostic>,
+    compiler_settings: CompilerSettings,
+}
+
+#[derive(Serialize, Debug)]
+struct PredictResponse {
+    contract: String,
+    chain: String,
+    #[serde(flatten)]
+    prediction: AddressPrediction,
+}
+
+#[derive(Serialize, Debug)]
+struct EstimateResponse {
+    contract: String,
+    chain: String,
+    #[serde(flatten)]
+    estimate: GasEstimate,
+    max_deploy_cost_eth: Option<String>, // The chain's limit, if any
+    within_limit: bool, // Whether a deploy request would be accepted at this estimate
+}
+
+/// Returned by `/api/deploy` as soon as the job is queued.
+#[derive(Serialize, Debug)]
+struct JobCreatedResponse {
+    job_id: String,
+    stage: JobStage,
+    status_url: String, // Poll this for the job's progress and result
+}
+
+#[derive(Serialize, Debug)]
+struct StatusResponse {
+    templates: ReloadStatus,
+    compile_cache: Option<CacheStats>, // None when the cache is disabled
+}
+
+#[derive(Serialize, Debug)]
+struct CompilersResponse {
+    compilers_dir: Option<PathBuf>, // Unset when every compilation uses `solc_path`
+    solc_path: String,
+    compilers: Vec<InstalledCompiler>,
 }
 
 // New response structure for the frontend
@@ -29,124 +194,738 @@ struct DeployRequest {
 struct FrontendDeployResponse {
     contract: String,
     address: String,
-    abi: String,
+    deployer: String, // Account that signed the creation transaction
+    abi: Abi,
+    tx_hash: String,
+    block_number: u64,
+    gas_used: u64,
     deployed_at: i64,
+    kyc_wallet: Option<String>, // Wallet whose KYC signature authorised this deployment
+    libraries: Vec<LinkStep>, // Libraries linked into the contract, and whether they were deployed now
+    create2: Option<Create2Info>, // Set when deployed with a salt
+    warnings: Vec<Diagnostic>, // Non-fatal compiler diagnostics for the generated source
+    compiler_settings: CompilerSettings,
+}
+
+/// Builds one DeployEngine per configured chain, all signing with the `[signer]` key.
+/// Without one, `DEPLOYER_PRIVATE_KEY` is used as a raw key, which needs dev mode.
+fn build_deploy_engines(
+    config: &AppConfig,
+    compilers: Option<&Arc<CompilerCache>>,
+    compile_cache: Option<&Arc<CompileCache>>,
+) -> HashMap<String, DeployEngine> {
+    let signer = config.signer.clone().or_else(|| {
+        env::var_os("DEPLOYER_PRIVATE_KEY").map(|_| SignerSource::PrivateKey {
+            key_env: Some("DEPLOYER_PRIVATE_KEY".to_string()),
+            key_file: None,
+        })
+    });
+    let deployer = match signer.map(|signer| signer.load(config.dev_mode)) {
+        Some(Ok(wallet)) => {
+            println!("Deploying from {}", checksum(&wallet.address()));
+            Some(wallet)
+        }
+        Some(Err(e)) => {
+            eprintln!("{}; deployments will fail.", e);
+            None
+        }
+        None => {
+            eprintln!("No [signer] is configured and DEPLOYER_PRIVATE_KEY is not set; deployments will fail.");
+            None
+        }
+    };
+
+    // Shared, so chains that are the same network under two names share nonces too.
+    let nonces = Arc::new(NonceManager::default());
+    config.chains.iter().map(|(name, chain)| {
+        println!("Using RPC endpoint for chain '{}': {}", name, chain.rpc_url);
+        let mut deploy_engine = DeployEngine::new(config.solc_path.clone(), chain.rpc_url.clone())
+            .with_compiler_settings(config.compiler.clone())
+            .with_libraries(chain.library_addresses())
+            .with_nonce_manager(Arc::clone(&nonces));
+        if let Some(factory) = chain.create2_factory.as_deref().and_then(|f| parse_address(f, false).ok()) {
+            deploy_engine = deploy_engine.with_create2_factory(factory);
+        }
+        deploy_engine = deploy_engine.with_legacy_fees(chain.legacy_fees).with_tracking(chain.tracking());
+        if let Some(max_cost) = chain.max_deploy_cost_wei() {
+            deploy_engine = deploy_engine.with_max_deploy_cost(max_cost);
+        }
+        if let Some(wallet) = &deployer {
+            deploy_engine = deploy_engine.with_deployer(wallet.clone());
+        }
+        if let Some(compilers) = compilers {
+            deploy_engine = deploy_engine.with_compiler_cache(Arc::clone(compilers));
+        }
+        if let Some(cache) = compile_cache {
+            deploy_engine = deploy_engine.with_compile_cache(Arc::clone(cache));
+        }
+        (name.clone(), deploy_engine)
+    }).collect()
+}
+
+impl AppState {
+    /// DeployEngine for `chain`, or for the default chain when `None`.
+    fn deploy_engine(&self, chain: Option<&str>) -> Result<&DeployEngine, ApiError> {
+        let chain = chain.unwrap_or(&self.config.default_chain);
+        self.deploy_engines.get(chain).ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "Unknown chain '{}'; configured chains: {}",
+                chain,
+                self.config.chains.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+        })
+    }
+}
+
+/// Renders a template without compiling, deploying or storing anything.
+async fn render_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<RenderRequest>, JsonRejection>,
+) -> Result<Json<RenderResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let template_engine = state.templates.engine();
+    let source = template_engine.render_template(&payload.contract, &payload.params)?;
+    Ok(Json(RenderResponse { contract: payload.contract, source }))
+}
+
+/// Renders and compiles a template without deploying or storing anything.
+async fn compile_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<RenderRequest>, JsonRejection>,
+) -> Result<Json<CompileResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let template_engine = state.templates.engine();
+    let rendered_solidity = template_engine.render_template(&payload.contract, &payload.params)?;
+
+    let compilation = off_runtime(move || {
+        let deploy_engine = state.deploy_engine(None)?;
+        let source_name = payload.contract.trim_end_matches(".tera");
+        let main_contract = template_engine.main_contract(&payload.contract);
+        let settings = template_engine.compiler_settings(&payload.contract).overlay(&payload.compiler_settings);
+        Ok(deploy_engine.compile_solidity(&rendered_solidity, source_name, &main_contract, &state.imports, &settings)?)
+    }).await?;
+    let artifact = compilation.main;
+
+    Ok(Json(CompileResponse {
+        bytecode_size: artifact.bytecode.len() / 2,
+        deployed_bytecode_size: artifact.deployed_bytecode.len() / 2,
+        contract: artifact.contract_name,
+        abi: artifact.abi,
+        bytecode: artifact.bytecode,
+        deployed_bytecode: artifact.deployed_bytecode,
+        metadata: artifact.metadata,
+        storage_layout: artifact.storage_layout,
+        method_identifiers: artifact.method_identifiers,
+        immutable_references: artifact.immutable_references,
+        source_map: artifact.source_map,
+        deployed_source_map: artifact.deployed_source_map,
+        compiler_version: artifact.compiler_version,
+        libraries: compilation.libraries,
+        helpers: compilation.helpers,
+        diagnostics: compilation.diagnostics,
+        compiler_settings: artifact.compiler_settings,
+    }))
+}
+
+/// Lists the available templates with their parameter schemas.
+async fn list_templates_handler(State(state): State<AppState>) -> Result<Json<Vec<TemplateInfo>>, ApiError> {
+    Ok(Json(state.templates.engine().list_templates()))
+}
+
+/// Returns one template's schema, raw source and imports.
+/// `name` may be given with or without the `.sol.tera` extension.
+async fn template_detail_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<TemplateDetail>, ApiError> {
+    let template_engine = state.templates.engine();
+    let template_name = if name.ends_with(".sol.tera") { name } else { format!("{}.sol.tera", name) };
+    Ok(Json(template_engine.template_detail(&template_name)?))
+}
+
+/// Reports server health: the outcome of the last template reload and compile cache stats.
+async fn status_handler(State(state): State<AppState>) -> Json<StatusResponse> {
+    Json(StatusResponse {
+        templates: state.templates.status(),
+        compile_cache: state.compile_cache.as_ref().map(|cache| cache.stats()),
+    })
+}
+
+/// Lists the compilers in the compiler cache, newest first, with their checksum status.
+async fn list_compilers_handler(State(state): State<AppState>) -> Result<Json<CompilersResponse>, ApiError> {
+    let compilers = match &state.compilers {
+        Some(cache) => cache.installed().map_err(|e| ApiError::from(DeployError::SolcError(e)))?,
+        None => Vec::new(),
+    };
+    Ok(Json(CompilersResponse {
+        compilers_dir: state.config.compilers_dir.clone(),
+        solc_path: state.config.solc_path.clone(),
+        compilers,
+    }))
+}
+
+/// Issues a single-use nonce that the wallet signs for the KYC block of a deploy request.
+async fn kyc_nonce_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<NonceRequest>, JsonRejection>,
+) -> Result<Json<IssuedNonce>, ApiError> {
+    let Json(payload) = payload?;
+    let wallet_address = parse_address(&payload.wallet_address, false)
+        .map_err(|e| ApiError::Kyc(format!("Invalid wallet address {}: {}", payload.wallet_address, e)))?;
+    Ok(Json(state.kyc_nonces.issue(wallet_address).map_err(ApiError::RateLimited)?))
+}
+
+/// Validates `params` and renders the template with them. The validated params, with
+/// manifest defaults filled in, are returned to feed the constructor.
+fn render_validated(
+    state: &AppState,
+    contract: &str,
+    params: &serde_json::Value,
+) -> Result<(serde_json::Value, String), ApiError> {
+    Ok(state.templates.engine().validate_and_render(contract, params)?)
+}
+
+/// Compiles the rendered source of template `contract` with its manifest's settings,
+/// overridden by `compiler_settings`.
+fn compile_rendered(
+    state: &AppState,
+    deploy_engine: &DeployEngine,
+    contract: &str,
+    rendered_solidity: &str,
+    compiler_settings: &CompilerSettings,
+) -> Result<Compilation, ApiError> {
+    let template_engine = state.templates.engine();
+    let source_name = contract.trim_end_matches(".tera");
+    let main_contract = template_engine.main_contract(contract);
+    let settings = template_engine.compiler_settings(contract).overlay(compiler_settings);
+    Ok(deploy_engine.compile_solidity(rendered_solidity, source_name, &main_contract, &state.imports, &settings)?)
+}
+
+/// Validates `params`, renders the template with them and compiles it.
+fn compile_template(
+    state: &AppState,
+    deploy_engine: &DeployEngine,
+    contract: &str,
+    params: &serde_json::Value,
+    compiler_settings: &CompilerSettings,
+) -> Result<(serde_json::Value, Compilation), ApiError> {
+    let (params, rendered_solidity) = render_validated(state, contract, params)?;
+    let compilation = compile_rendered(state, deploy_engine, contract, &rendered_solidity, compiler_settings)?;
+    Ok((params, compilation))
+}
+
+/// Runs `compile` on the blocking thread pool, so that solc, which can take a while,
+/// does not hold up the async runtime.
+async fn off_runtime<T: Send + 'static>(
+    compile: impl FnOnce() -> Result<T, ApiError> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(compile)
+        .await
+        .map_err(|e| ApiError::from(DeployError::SolcError(format!("Compilation task failed: {}", e))))?
+}
+
+/// Renders and compiles the template of a job, off the async runtime since solc can
+/// take a while.
+async fn compile_job(state: &AppState, job: &DeployJob) -> Result<(serde_json::Value, Compilation), ApiError> {
+    state.jobs.update(&job.id, |job| job.set_stage(JobStage::Rendering));
+    let (params, rendered_solidity) = render_validated(state, &job.request.contract, &job.request.params)?;
+
+    state.jobs.update(&job.id, |job| job.set_stage(JobStage::Compiling));
+    let (task_state, request) = (state.clone(), job.request.clone());
+    let compilation = off_runtime(move || {
+        let deploy_engine = task_state.deploy_engine(Some(&request.chain))?;
+        compile_rendered(&task_state, deploy_engine, &request.contract, &rendered_solidity, &request.compiler_settings)
+    }).await?;
+    println!("Compilation successful for {}", compilation.main.contract_name);
+    state.jobs.update(&job.id, |job| job.main_contract = Some(compilation.main.qualified_name()));
+    state.jobs.publish(&job.id, JobEvent::Compiled {
+        contract: compilation.main.qualified_name(),
+        warnings: compilation.diagnostics.clone(),
+    });
+    Ok((params, compilation))
 }
 
-async fn deploy_handler(Json(payload): Json<DeployRequest>) -> Json<FrontendDeployResponse> {
+/// Predicts the address a deploy request with the same body and salt would get,
+/// without sending anything.
+async fn predict_address_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<PredictRequest>, JsonRejection>,
+) -> Result<Json<PredictResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let config = &state.config;
+    let chain = payload.chain.as_deref().unwrap_or(&config.default_chain);
+    let deploy_engine = state.deploy_engine(Some(chain))?;
+    let salt = parse_salt(&payload.salt).map_err(ApiError::BadRequest)?;
+
+    let (task_state, task_chain) = (state.clone(), chain.to_string());
+    let (contract, params, settings) = (payload.contract, payload.params, payload.compiler_settings);
+    let (params, compilation) = off_runtime(move || {
+        let deploy_engine = task_state.deploy_engine(Some(&task_chain))?;
+        compile_template(&task_state, deploy_engine, &contract, &params, &settings)
+    }).await?;
+    let prediction = deploy_engine.predict_address(&compilation, &params, salt, |library| {
+        find_deployed(&config.deployments_dir, chain, library)
+    })?;
+    Ok(Json(PredictResponse {
+        contract: compilation.main.contract_name,
+        chain: chain.to_string(),
+        prediction,
+    }))
+}
+
+async fn estimate_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<EstimateRequest>, JsonRejection>,
+) -> Result<Json<EstimateResponse>, ApiError> {
+    let Json(payload) = payload?;
+    let config = &state.config;
+    let chain = payload.chain.as_deref().unwrap_or(&config.default_chain);
+    let deploy_engine = state.deploy_engine(Some(chain))?;
+    let salt = payload.salt.as_deref().map(parse_salt).transpose().map_err(ApiError::BadRequest)?;
+
+    let (task_state, task_chain) = (state.clone(), chain.to_string());
+    let (contract, params, settings) = (payload.contract, payload.params, payload.compiler_settings);
+    let (params, compilation) = off_runtime(move || {
+        let deploy_engine = task_state.deploy_engine(Some(&task_chain))?;
+        compile_template(&task_state, deploy_engine, &contract, &params, &settings)
+    }).await?;
+    let options = DeployOptions { salt, fee: payload.fee, events: None };
+    let estimate = deploy_engine.estimate_deployment(&compilation, &params, &options, |library| {
+        find_deployed(&config.deployments_dir, chain, library)
+    }).await?;
+    let max_cost = deploy_engine.max_deploy_cost();
+    Ok(Json(EstimateResponse {
+        contract: compilation.main.contract_name,
+        chain: chain.to_string(),
+        within_limit: max_cost.is_none_or(|max| estimate.total_cost_wei <= max),
+        max_deploy_cost_eth: max_cost.map(ethers::utils::format_ether),
+        estimate,
+    }))
+}
+
+/// Queues a deployment. KYC, the chain and the salt are checked right away; rendering,
+/// compiling and deploying happen in a background job, polled at `/api/jobs/:id`.
+async fn deploy_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<DeployRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<JobCreatedResponse>), ApiError> {
+    let Json(payload) = payload?;
     println!("Received deploy request for contract template: {}", payload.contract);
     println!("Params: {:?}", payload.params);
 
     // --- KYC Validation ---
-    // Perform KYC check with hardcoded values early in the handler.
-    if let Err(kyc_error_msg) = simulate_kyc_validation(
-        "Test User",
-        "0x1234567890123456789012345678901234567890",
-        "testhash",
-    ) {
-        eprintln!("KYC validation failed: {}", kyc_error_msg);
-        return Json(FrontendDeployResponse {
-            contract: String::new(), // Or payload.contract if you want to return the requested contract name
-            address: String::new(),
-            abi: String::new(),
-            deployed_at: 0,
-        });
-    }
-    println!("KYC validation successful."); // Optional: log success
+    // The signature over the issued nonce must recover to the claimed wallet.
+    let kyc_wallet = match (state.config.kyc_mode, &payload.kyc) {
+        (KycMode::Disabled, _) => None,
+        (KycMode::Signature, None) => return Err(ApiError::Kyc("A kyc block is required.".to_string())),
+        (KycMode::Signature, Some(kyc)) => {
+            let wallet = state.kyc_nonces.verify(kyc).map_err(ApiError::Kyc)?;
+            println!("KYC validation successful for {:?}.", wallet);
+            Some(wallet)
+        }
+    };
 
     // --- Configuration ---
-    let contracts_base_dir = PathBuf::from("../../contracts") // Relative to executable in target/debug or if run from workspace root
-        .canonicalize()
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to canonicalize contracts_base_dir: {:?}. Using relative path.", e);
-            PathBuf::from("../../contracts")
-        });
-    
-    println!("Using contracts base directory: {:?}", contracts_base_dir);
-
-    let solc_executable = env::var("SOLC_PATH").unwrap_or_else(|_| "solc".to_string());
-    println!("Using SOLC executable: {}", solc_executable);
-    
-    let solc_remappings = vec![
-        "@openzeppelin/contracts/=lib/openzeppelin-repo/contracts/".to_string(),
-    ];
-    println!("Using SOLC remappings: {:?}", solc_remappings);
-
-    let template_engine = match SolTemplateEngine::new(contracts_base_dir.clone()) {
-        Ok(engine) => engine,
-        Err(e) => {
-            eprintln!("Failed to initialize SolTemplateEngine: {:?}", e);
-            // Return a default error response
-            return Json(FrontendDeployResponse {
-                contract: payload.contract,
-                address: String::new(),
-                abi: String::new(),
-                deployed_at: 0,
-            });
+    let chain = payload.chain.unwrap_or_else(|| state.config.default_chain.clone());
+    state.deploy_engine(Some(&chain))?;
+    if let Some(salt) = payload.salt.as_deref() {
+        parse_salt(salt).map_err(ApiError::BadRequest)?;
+    }
+
+    let job = state.jobs.create(JobRequest {
+        contract: payload.contract,
+        params: payload.params,
+        chain,
+        compiler_settings: payload.compiler_settings,
+        salt: payload.salt,
+        fee: payload.fee,
+        kyc_wallet,
+    });
+    println!("Queued deployment job {} for {}", job.id, job.request.contract);
+    tokio::spawn(run_job(state.clone(), job.id.clone(), HashMap::new()));
+    Ok((StatusCode::ACCEPTED, Json(JobCreatedResponse {
+        status_url: format!("/api/jobs/{}", job.id),
+        job_id: job.id,
+        stage: job.stage,
+    })))
+}
+
+/// Returns a deployment job: its stage, timestamps, transactions and result or error.
+async fn job_handler(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<DeployJob>, ApiError> {
+    state.jobs.get(&id).map(Json).ok_or(ApiError::JobNotFound(id))
+}
+
+/// Streams a job's progress as server-sent events. The first event, `job`, is the job
+/// as `/api/jobs/:id` returns it; every event after it is named after its `event` field.
+/// The stream ends with `finished`, or right after `job` if the job is already over.
+async fn job_events_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // Subscribed before the snapshot is taken, so nothing falls in between.
+    let receiver = state.jobs.subscribe();
+    let job = state.jobs.get(&id).ok_or_else(|| ApiError::JobNotFound(id.clone()))?;
+    let snapshot = Event::default().event("job").data(serde_json::json!(job).to_string());
+
+    let live = futures::stream::unfold((receiver, job.stage.is_finished()), move |(mut receiver, done)| {
+        let (id, jobs) = (id.clone(), Arc::clone(&state.jobs));
+        async move {
+            if done {
+                return None;
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(update) if update.job_id == id => {
+                        let done = matches!(update.event, JobEvent::Finished { .. });
+                        let data = serde_json::json!(update.event);
+                        let name = data["event"].as_str().unwrap_or("message").to_string();
+                        return Some((Ok(Event::default().event(name).data(data.to_string())), (receiver, done)));
+                    }
+                    Ok(_) => continue,
+                    // Too slow to keep up: send the whole job again so the client can catch up.
+                    Err(RecvError::Lagged(_)) => {
+                        let job = jobs.get(&id)?;
+                        let event = Event::default().event("job").data(serde_json::json!(job).to_string());
+                        return Some((Ok(event), (receiver, job.stage.is_finished())));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
         }
+    });
+    Ok(Sse::new(futures::stream::once(async { Ok(snapshot) }).chain(live)).keep_alive(KeepAlive::default()))
+}
+
+/// Runs job `id` to the end and records its outcome. `recovered` maps libraries that an
+/// earlier run of the job already deployed to their address, so they are reused.
+async fn run_job(state: AppState, id: String, recovered: HashMap<String, Address>) {
+    let Some(job) = state.jobs.get(&id) else {
+        return;
     };
+    let outcome = execute_job(&state, &job, &recovered).await;
+    finish_job(&state, &id, outcome);
+}
 
-    let deploy_engine = DeployEngine::new(solc_executable.clone());
+fn finish_job(state: &AppState, id: &str, outcome: Result<FrontendDeployResponse, ApiError>) {
+    state.jobs.update(id, |job| match outcome {
+        Ok(response) => {
+            println!("Deployment job {} succeeded: {} at {}", id, response.contract, response.address);
+            job.succeed(serde_json::json!(response));
+        }
+        Err(error) => {
+            eprintln!("Deployment job {} failed: {}", id, error);
+            job.fail(JobError::from(&error));
+        }
+    });
+}
 
-    let rendered_solidity = match template_engine.render_template(&payload.contract, &payload.params) {
-        Ok(code) => code,
-        Err(e) => {
-            eprintln!("Failed to render template {}: {:?}", payload.contract, e);
-            return Json(FrontendDeployResponse {
-                contract: payload.contract,
-                address: String::new(),
-                abi: String::new(),
-                deployed_at: 0,
-            });
+async fn execute_job(
+    state: &AppState,
+    job: &DeployJob,
+    recovered: &HashMap<String, Address>,
+) -> Result<FrontendDeployResponse, ApiError> {
+    let request = &job.request;
+    let config = &state.config;
+    let chain = request.chain.as_str();
+    let deploy_engine = state.deploy_engine(Some(chain))?;
+    let salt = request.salt.as_deref().map(parse_salt).transpose().map_err(ApiError::BadRequest)?;
+    let (params, compilation) = compile_job(state, job).await?;
+
+    let (events, forwarder) = forward_events(state, &job.id);
+    let options = DeployOptions { salt, fee: request.fee, events: Some(events) };
+    let deployment = deploy_engine.deploy_linked(&compilation, &params, &options, |library| {
+        recovered.get(&library.qualified_name()).copied()
+            .or_else(|| find_deployed(&config.deployments_dir, chain, library))
+    }).await;
+    drop(options);
+    let _ = forwarder.await;
+    let deployment = deployment?;
+    println!("Deployed {} at {:?}", compilation.main.contract_name, deployment.address);
+
+    Ok(store_deployment(state, &job.id, chain, &compilation, deployment, request.kyc_wallet))
+}
+
+/// Records the deploy engine's events in job `id` and publishes them, until the returned
+/// sender is dropped. Progress is recorded as it happens, so the transactions of a job
+/// are known if the server stops halfway.
+fn forward_events(state: &AppState, id: &str) -> (UnboundedSender<DeployEvent>, tokio::task::JoinHandle<()>) {
+    let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let (jobs, id) = (Arc::clone(&state.jobs), id.to_string());
+    let forwarder = tokio::spawn(async move {
+        while let Some(event) = received.recv().await {
+            jobs.update(&id, |job| job.apply(&event));
+            jobs.publish(&id, JobEvent::Deploy(event));
         }
-    };
+    });
+    (events, forwarder)
+}
 
-    let contract_name_to_compile = payload.contract.replace(".sol.tera", "");
-
-    match deploy_engine.compile_solidity(&rendered_solidity, &contract_name_to_compile, &contracts_base_dir, &solc_remappings) {
-        Ok(comp_output) => {
-            println!("Compilation successful for {}", comp_output.contract_name);
-            
-            // Simulate deployment address (as it was before)
-            let simulated_address = "0xSIMULATED_ADDRESS_MAIN_RS".to_string(); // Placeholder
-            let deployed_at_ts = Utc::now().timestamp();
-
-            // Store the artifact
-            // For now, log error from store_artifact and continue. 
-            // A more robust solution would involve returning an error response to the client.
-            if let Err(e) = store_artifact(&comp_output, &simulated_address) {
-                eprintln!("Failed to store artifact for {}: {:?}", comp_output.contract_name, e);
-                // Depending on requirements, you might want to return an error here.
-                // For now, we proceed to return success response as compilation & simulation were okay.
-            }
+/// Stores the artifacts of a deployment and of the libraries deployed with it, and
+/// builds the response reported for it.
+fn store_deployment(
+    state: &AppState,
+    job_id: &str,
+    chain: &str,
+    compilation: &Compilation,
+    deployment: DeploymentResult,
+    kyc_wallet: Option<Address>,
+) -> FrontendDeployResponse {
+    let config = &state.config;
+    let comp_output = &compilation.main;
+    let deployed_at_ts = Utc::now().timestamp();
 
-            Json(FrontendDeployResponse {
-                contract: comp_output.contract_name.clone(),
-                address: simulated_address,
-                abi: comp_output.abi.clone(), // ABI is now String
-                deployed_at: deployed_at_ts,
-            })
+    // Libraries deployed along the way are stored too, so later deployments reuse them.
+    for step in deployment.libraries.iter().filter(|step| step.source == LibrarySource::Deployed) {
+        let Some(library) = compilation.libraries.iter().find(|l| l.qualified_name() == step.library) else {
+            continue;
+        };
+        let library_deployment = DeploymentResult {
+            address: step.address,
+            deployer: deployment.deployer,
+            tx_hash: step.tx_hash.unwrap_or_default(),
+            block_number: step.block_number.unwrap_or_default(),
+            gas_used: step.gas_used.unwrap_or_default(),
+            libraries: Vec::new(),
+            create2: None,
+        };
+        match store_artifact(library, &library_deployment, &config.deployments_dir, chain) {
+            Ok(path) => state.jobs.publish(job_id, JobEvent::ArtifactStored {
+                contract: step.library.clone(),
+                address: step.address,
+                path,
+            }),
+            Err(e) => warn_job(state, job_id, format!("Could not store the artifact of library {}: {}", step.library, e)),
         }
-        Err(e) => {
-            eprintln!("Failed to compile Solidity for {}: {:?}", contract_name_to_compile, e);
-            // Return a default error response
-            Json(FrontendDeployResponse {
-                contract: contract_name_to_compile, // Use the name we tried to compile
-                address: String::new(),
-                abi: String::new(),
-                deployed_at: 0,
-            })
+    }
+
+    // The contract is already on-chain at this point, so a failure to store its artifact
+    // is only a warning: failing the job would have the client deploy it again.
+    match store_artifact(comp_output, &deployment, &config.deployments_dir, chain) {
+        Ok(path) => state.jobs.publish(job_id, JobEvent::ArtifactStored {
+            contract: comp_output.qualified_name(),
+            address: deployment.address,
+            path,
+        }),
+        Err(e) => warn_job(state, job_id, format!(
+            "{} was deployed at {} but its artifact could not be stored: {}",
+            comp_output.qualified_name(), checksum(&deployment.address), e
+        )),
+    }
+
+    FrontendDeployResponse {
+        contract: comp_output.contract_name.clone(),
+        address: checksum(&deployment.address),
+        deployer: checksum(&deployment.deployer),
+        abi: comp_output.abi.clone(),
+        tx_hash: format!("{:?}", deployment.tx_hash),
+        block_number: deployment.block_number,
+        gas_used: deployment.gas_used,
+        deployed_at: deployed_at_ts,
+        kyc_wallet: kyc_wallet.as_ref().map(checksum),
+        libraries: deployment.libraries.clone(),
+        create2: deployment.create2.clone(),
+        warnings: compilation.diagnostics.clone(),
+        compiler_settings: comp_output.compiler_settings.clone(),
+    }
+}
+
+/// Records a warning in job `id` and publishes it.
+fn warn_job(state: &AppState, id: &str, message: String) {
+    eprintln!("Deployment job {}: {}", id, message);
+    state.jobs.update(id, |job| job.warn(message.clone()));
+    state.jobs.publish(id, JobEvent::Warning { message });
+}
+
+/// Picks up a job a previous run of the server left unfinished. A job that sent nothing
+/// runs again from the start. Otherwise its transactions are looked up on-chain first,
+/// and followed to the chain's confirmation depth, so nothing is deployed twice: if the
+/// main contract was mined the job is completed from its receipt, and if not it runs
+/// again, reusing the libraries already mined.
+async fn resume_job(state: AppState, job: DeployJob) {
+    println!("Resuming deployment job {} from stage {:?}", job.id, job.stage);
+    if !job.has_broadcast() {
+        return run_job(state, job.id, HashMap::new()).await;
+    }
+    match reconcile_job(&state, &job).await {
+        Ok(Reconciled { deployment: Some(deployment), mined }) => {
+            let outcome = complete_job(&state, &job, *deployment, &mined).await;
+            finish_job(&state, &job.id, outcome);
+        }
+        Ok(Reconciled { deployment: None, mined }) => run_job(state, job.id, mined).await,
+        Err(error) => finish_job(&state, &job.id, Err(error)),
+    }
+}
+
+struct Reconciled {
+    deployment: Option<Box<DeploymentResult>>, // Set when the main contract is on-chain
+    mined: HashMap<String, Address>, // Libraries already mined, by qualified name
+}
+
+/// Stores a deployment found on-chain while resuming `job`. The template is compiled
+/// again for the artifact, and for the CREATE2 details when the job has a salt.
+async fn complete_job(
+    state: &AppState,
+    job: &DeployJob,
+    mut deployment: DeploymentResult,
+    mined: &HashMap<String, Address>,
+) -> Result<FrontendDeployResponse, ApiError> {
+    let chain = job.request.chain.as_str();
+    let (params, compilation) = compile_job(state, job).await?;
+    if let Some(salt) = job.request.salt.as_deref().map(parse_salt).transpose().map_err(ApiError::BadRequest)? {
+        let prediction = state.deploy_engine(Some(chain))?.predict_address(&compilation, &params, salt, |library| {
+            mined.get(&library.qualified_name()).copied()
+                .or_else(|| find_deployed(&state.config.deployments_dir, chain, library))
+        })?;
+        deployment.create2 = Some(prediction.create2);
+    }
+    Ok(store_deployment(state, &job.id, chain, &compilation, deployment, job.request.kyc_wallet))
+}
+
+/// Waits for the recorded transactions of `job` to be final or dropped. Replacements
+/// share the nonce of the transaction they replace, and count as one transaction.
+async fn reconcile_job(state: &AppState, job: &DeployJob) -> Result<Reconciled, ApiError> {
+    let deploy_engine = state.deploy_engine(Some(&job.request.chain))?;
+    let mut nonces: Vec<U256> = job.transactions.iter().map(|tx| tx.nonce).collect();
+    nonces.sort();
+    nonces.dedup();
+
+    let (events, forwarder) = forward_events(state, &job.id);
+    let mut mined = HashMap::new();
+    let mut main_deployment = None;
+    let mut outcome = Ok(());
+    for nonce in nonces {
+        let versions: Vec<_> = job.transactions.iter().filter(|tx| tx.nonce == nonce).collect();
+        let hashes = versions.iter().map(|tx| tx.tx_hash).collect();
+        let receipt = match deploy_engine.confirm_sent(&versions[0].contract, nonce, hashes, Some(&events)).await {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => {
+                println!("Transaction {:?} of job {} was dropped; it will be sent again", versions[0].tx_hash, job.id);
+                continue;
+            }
+            Err(e) => {
+                outcome = Err(e);
+                break;
+            }
+        };
+        let tx = versions.iter().find(|tx| tx.tx_hash == receipt.transaction_hash).unwrap_or(&versions[0]);
+        if job.main_contract.as_ref() == Some(&tx.contract) {
+            main_deployment = Some(Box::new(DeploymentResult {
+                address: tx.address,
+                deployer: receipt.from,
+                tx_hash: receipt.transaction_hash,
+                block_number: receipt.block_number.unwrap_or_default().as_u64(),
+                gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+                libraries: job.libraries.clone(),
+                create2: None,
+            }));
+        } else {
+            mined.insert(tx.contract.clone(), tx.address);
         }
     }
+    drop(events);
+    let _ = forwarder.await;
+    outcome?;
+
+    Ok(Reconciled { deployment: main_deployment, mined })
 }
 
 #[tokio::main]
 async fn main() {
-    let app = Router::new().route("/api/deploy", post(deploy_handler));
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+    let config = match AppConfig::load(&CliArgs::parse()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Using contracts base directory: {:?}", config.contracts_dir);
+    match &config.compilers_dir {
+        Some(dir) => println!("Using SOLC compilers from: {:?}", dir),
+        None => println!("Using SOLC executable: {}", config.solc_path),
+    }
+    let imports = Arc::new(ImportResolver::new(config.contracts_dir.clone(), &config.libraries, config.remappings.clone()));
+    println!("Using SOLC remappings: {:?}", imports.remappings());
+    if config.kyc_mode == KycMode::Disabled {
+        eprintln!("KYC is disabled; deploy requests are not checked.");
+    }
+    let addr = config.bind_address;
+
+    let templates = match TemplateStore::new(config.contracts_dir.clone()) {
+        Ok(templates) => Arc::new(templates),
+        Err(e) => {
+            eprintln!("Failed to load templates from {:?}: {}", config.contracts_dir, e);
+            std::process::exit(1);
+        }
+    };
+    // Kept alive for the lifetime of the server; dropping it stops hot reload.
+    let _template_watcher = match templates.watch() {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            eprintln!("Could not watch {:?} for template changes; hot reload is disabled: {}", config.contracts_dir, e);
+            None
+        }
+    };
+    let compilers = config.compilers_dir.clone().map(|dir| Arc::new(CompilerCache::new(dir)));
+    let compile_cache = if config.compile_cache.enabled {
+        match CompileCache::new(config.compile_cache.dir.clone(), config.compile_cache.max_entries) {
+            Ok(cache) => {
+                println!("Caching compilations in {:?}", config.compile_cache.dir);
+                Some(Arc::new(cache))
+            }
+            Err(e) => {
+                eprintln!("Could not create compile cache at {:?}; compilations will not be cached: {}", config.compile_cache.dir, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let deploy_engines = Arc::new(build_deploy_engines(&config, compilers.as_ref(), compile_cache.as_ref()));
+    let jobs = match JobStore::new(config.jobs_dir.clone()) {
+        Ok(jobs) => Arc::new(jobs),
+        Err(e) => {
+            eprintln!("Failed to open the jobs directory {:?}: {}", config.jobs_dir, e);
+            std::process::exit(1);
+        }
+    };
+    let state = AppState {
+        config: Arc::new(config),
+        kyc_nonces: Arc::new(NonceStore::new()),
+        templates,
+        deploy_engines,
+        compilers,
+        compile_cache,
+        imports,
+        jobs,
+    };
+    for job in state.jobs.unfinished() {
+        tokio::spawn(resume_job(state.clone(), job));
+    }
+
+    let app = Router::new()
+        .route("/api/kyc/nonce", post(kyc_nonce_handler))
+        .route("/api/deploy", post(deploy_handler))
+        .route("/api/jobs/:id", get(job_handler))
+        .route("/api/jobs/:id/events", get(job_events_handler))
+        .route("/api/predict-address", post(predict_address_handler))
+        .route("/api/estimate", post(estimate_handler))
+        .route("/api/render", post(render_handler))
+        .route("/api/compile", post(compile_handler))
+        .route("/api/templates", get(list_templates_handler))
+        .route("/api/templates/:name", get(template_detail_handler))
+        .route("/api/status", get(status_handler))
+        .route("/api/compilers", get(list_compilers_handler))
+        .with_state(state);
     println!("Backend server listening on {}", addr);
 
-    if let Err(e) = axum::Server::bind(&addr).serve(app.into_make_service()).await {
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", addr, e);
+            return;
+        }
+    };
+    if let Err(e) = axum::serve(listener, app).await {
         eprintln!("Server error: {}", e);
     }
 }
@@ -0,0 +1,193 @@
+use ethers::types::{Address, U256};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Hands out the nonces of deployer accounts, per chain, so that concurrent deployments
+/// from one key each get their own, in order.
+///
+/// An account's first nonce is the node's pending transaction count. A nonce stays
+/// reserved until its transaction is sent, or it is settled otherwise. One whose
+/// transaction never reached the node is released and handed out again before any new
+/// one, so it leaves no gap. After an error that leaves the count in doubt, such as a
+/// rejected or dropped transaction, the account is resynced: its next nonce is read from
+/// the node again. Nonces other deployments still hold are never handed out again by a
+/// resync; those below them that the node does not know of are gaps, and are handed out
+/// first.
+///
+/// Each account has its own lock, so a slow node only holds up the accounts it serves.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    // Keyed by chain id and account; the map's lock is only held to find an entry.
+    accounts: std::sync::Mutex<HashMap<(u64, Address), SharedAccount>>,
+}
+
+type SharedAccount = Arc<Mutex<AccountNonces>>;
+
+#[derive(Debug, Default)]
+struct AccountNonces {
+    next: Option<U256>, // Unset until read from the node
+    reserved: BTreeSet<U256>, // Handed out and not yet sent
+    released: BTreeSet<U256>, // Handed out before, free again
+}
+
+impl NonceManager {
+    fn account(&self, chain_id: u64, address: Address) -> SharedAccount {
+        Arc::clone(self.accounts.lock().unwrap().entry((chain_id, address)).or_default())
+    }
+
+    fn existing_account(&self, chain_id: u64, address: Address) -> Option<SharedAccount> {
+        self.accounts.lock().unwrap().get(&(chain_id, address)).cloned()
+    }
+
+    /// Reserves the next nonce of `address` on chain `chain_id`. `pending_count` reads
+    /// the node's pending transaction count, and is only awaited when the account has no
+    /// known next nonce.
+    pub async fn reserve<E>(
+        &self,
+        chain_id: u64,
+        address: Address,
+        pending_count: impl Future<Output = Result<U256, E>>,
+    ) -> Result<U256, E> {
+        // Held while the count is read, so concurrent first reservations read it once.
+        let account = self.account(chain_id, address);
+        let mut account = account.lock().await;
+        if account.next.is_none() {
+            let pending = pending_count.await?;
+            account.released.retain(|nonce| *nonce >= pending);
+            // Up to the last nonce still reserved, those the node does not know are gaps.
+            let mut next = pending;
+            if let Some(&last) = account.reserved.last() {
+                while next <= last {
+                    if !account.reserved.contains(&next) {
+                        account.released.insert(next);
+                    }
+                    next += U256::one();
+                }
+            }
+            account.next = Some(next);
+        }
+        let nonce = match account.released.pop_first() {
+            Some(nonce) => nonce,
+            None => {
+                let next = account.next.unwrap_or_default();
+                account.next = Some(next + 1);
+                next
+            }
+        };
+        account.reserved.insert(nonce);
+        Ok(nonce)
+    }
+
+    /// Returns `nonce`, whose transaction never reached the node, to be reserved again.
+    pub async fn release(&self, chain_id: u64, address: Address, nonce: U256) {
+        if let Some(account) = self.existing_account(chain_id, address) {
+            let mut account = account.lock().await;
+            if account.reserved.remove(&nonce) {
+                account.released.insert(nonce);
+            }
+        }
+    }
+
+    /// Ends the reservation of `nonce` for good: its transaction was sent, or the node
+    /// already has a transaction with it.
+    pub async fn settle(&self, chain_id: u64, address: Address, nonce: U256) {
+        if let Some(account) = self.existing_account(chain_id, address) {
+            account.lock().await.reserved.remove(&nonce);
+        }
+    }
+
+    /// Makes the next reservation of `address` on chain `chain_id` read the pending count
+    /// from the node again. Nonces still reserved are kept, so they are not handed out twice.
+    pub async fn resync(&self, chain_id: u64, address: Address) {
+        if let Some(account) = self.existing_account(chain_id, address) {
+            account.lock().await.next = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn reserve(nonces: &NonceManager, address: Address, pending: u64) -> u64 {
+        nonces.reserve(1, address, async { Ok::<_, String>(U256::from(pending)) }).await.unwrap().as_u64()
+    }
+
+    #[tokio::test]
+    async fn test_nonces_are_handed_out_in_order() {
+        let nonces = NonceManager::default();
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        assert_eq!(reserve(&nonces, alice, 5).await, 5);
+        // The node is only asked once; later counts are ignored.
+        assert_eq!(reserve(&nonces, alice, 0).await, 6);
+        assert_eq!(reserve(&nonces, bob, 0).await, 0);
+        let other_chain = nonces.reserve(2, alice, async { Ok::<_, String>(U256::from(9)) }).await.unwrap();
+        assert_eq!(other_chain, U256::from(9));
+
+        let cached = nonces.reserve(1, alice, async { Err("unreachable".to_string()) }).await;
+        assert_eq!(cached, Ok(U256::from(7)));
+        for sent in 5..8 {
+            nonces.settle(1, alice, U256::from(sent)).await;
+        }
+        nonces.resync(1, alice).await;
+        let failed = nonces.reserve(1, alice, async { Err("node down".to_string()) }).await;
+        assert_eq!(failed, Err("node down".to_string()));
+        assert_eq!(reserve(&nonces, alice, 7).await, 7);
+    }
+
+    #[tokio::test]
+    async fn test_slow_node_only_holds_up_its_account() {
+        let nonces = Arc::new(NonceManager::default());
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let stuck = Arc::clone(&nonces);
+        tokio::spawn(async move { stuck.reserve(1, alice, std::future::pending::<Result<U256, String>>()).await });
+        tokio::task::yield_now().await;
+
+        let other = tokio::time::timeout(std::time::Duration::from_secs(1), reserve(&nonces, bob, 3)).await;
+        assert_eq!(other, Ok(3));
+        nonces.settle(1, bob, U256::from(3)).await;
+        let same = tokio::time::timeout(std::time::Duration::from_millis(50), reserve(&nonces, alice, 0)).await;
+        assert!(same.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_released_nonces_fill_the_gap() {
+        let nonces = NonceManager::default();
+        let alice = Address::repeat_byte(1);
+        for expected in 0..4 {
+            assert_eq!(reserve(&nonces, alice, 0).await, expected);
+        }
+        nonces.release(1, alice, U256::from(2)).await;
+        nonces.release(1, alice, U256::from(1)).await;
+        nonces.release(1, alice, U256::from(10)).await; // Never handed out
+        assert_eq!(reserve(&nonces, alice, 0).await, 1);
+        assert_eq!(reserve(&nonces, alice, 0).await, 2);
+        assert_eq!(reserve(&nonces, alice, 0).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_resync_keeps_reserved_nonces() {
+        let nonces = NonceManager::default();
+        let alice = Address::repeat_byte(1);
+        for expected in 0..4 {
+            assert_eq!(reserve(&nonces, alice, 0).await, expected);
+        }
+        // 0 was sent and 1 was rejected; 2 and 3 are still being signed elsewhere.
+        nonces.settle(1, alice, U256::zero()).await;
+        nonces.settle(1, alice, U256::one()).await;
+        nonces.resync(1, alice).await;
+        assert_eq!(reserve(&nonces, alice, 1).await, 1);
+        assert_eq!(reserve(&nonces, alice, 1).await, 4);
+
+        // 1 to 4 were sent, but the node lost 4: a gap below 5, still reserved.
+        for sent in 1..5 {
+            nonces.settle(1, alice, U256::from(sent)).await;
+        }
+        assert_eq!(reserve(&nonces, alice, 1).await, 5);
+        nonces.resync(1, alice).await;
+        assert_eq!(reserve(&nonces, alice, 4).await, 4);
+        assert_eq!(reserve(&nonces, alice, 4).await, 6);
+    }
+}
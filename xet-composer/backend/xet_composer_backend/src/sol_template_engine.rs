@@ -1,6 +1,12 @@
 use tera::{Context, Tera};
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 use serde::Serialize; // Required for context
+use serde_json::Value;
+
+use crate::solc_standard_json::CompilerSettings;
+use crate::template_schema::{TemplateSchema, ValidationIssue};
 
 // Error type for this module
 #[derive(Debug)]
@@ -8,6 +14,26 @@ pub enum TemplateError {
     IoError(std::io::Error),
     TeraError(tera::Error),
     TemplateNotFound(String),
+    InvalidSchema(String), // A `*.schema.json` manifest could not be read or is inconsistent
+    ValidationFailed(Vec<ValidationIssue>),
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::IoError(e) => write!(f, "I/O error: {}", e),
+            TemplateError::TeraError(e) => write!(f, "Template error: {}", e),
+            TemplateError::TemplateNotFound(name) => write!(f, "Template not found: {}", name),
+            TemplateError::InvalidSchema(msg) => write!(f, "Invalid template schema: {}", msg),
+            TemplateError::ValidationFailed(issues) => {
+                write!(f, "Invalid template parameters:")?;
+                for issue in issues {
+                    write!(f, " {}: {};", issue.field, issue.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl From<std::io::Error> for TemplateError {
@@ -22,9 +48,27 @@ impl From<tera::Error> for TemplateError {
     }
 }
 
+/// Catalog entry for a loaded template.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub schema: TemplateSchema,
+}
+
+/// Catalog entry plus the raw template source and the paths it imports.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateDetail {
+    #[serde(flatten)]
+    pub info: TemplateInfo,
+    pub source: String,
+    pub imports: Vec<String>,
+}
+
 pub struct SolTemplateEngine {
     tera: Tera,
-    contracts_dir: PathBuf,
+    schemas: HashMap<String, TemplateSchema>, // Keyed by template name, e.g. "TokenVesting.sol.tera"
+    sources: HashMap<String, String>, // Raw template sources as loaded, keyed like `schemas`
 }
 
 impl SolTemplateEngine {
@@ -42,29 +86,128 @@ impl SolTemplateEngine {
             Ok(t) => t,
             Err(e) => return Err(TemplateError::TeraError(e)),
         };
-        Ok(Self { tera: tera_instance, contracts_dir: contracts_base_dir })
+
+        // Each `Name.sol.tera` may have a sidecar `Name.schema.json` manifest.
+        let mut schemas = HashMap::new();
+        let mut sources = HashMap::new();
+        for template_name in tera_instance.get_template_names() {
+            let source = fs::read_to_string(contracts_base_dir.join(template_name))?;
+            sources.insert(template_name.to_string(), source);
+            let schema_path = contracts_base_dir
+                .join(format!("{}.schema.json", template_name.trim_end_matches(".sol.tera")));
+            if schema_path.exists() {
+                let schema = TemplateSchema::load(&schema_path).map_err(TemplateError::InvalidSchema)?;
+                schemas.insert(template_name.to_string(), schema);
+            } else {
+                eprintln!("No parameter manifest for {} at {:?}; params will not be validated.", template_name, schema_path);
+            }
+        }
+
+        Ok(Self { tera: tera_instance, schemas, sources })
+    }
+
+    /// Checks `params` against the template's parameter manifest and returns them with
+    /// defaults applied. Templates without a manifest get their params back unchanged.
+    pub fn validate_params(&self, template_name: &str, params: &Value) -> Result<Value, TemplateError> {
+        if self.tera.get_template_names().all(|name| name != template_name) {
+            return Err(TemplateError::TemplateNotFound(template_name.to_string()));
+        }
+        match self.schemas.get(template_name) {
+            Some(schema) => schema.validate(params).map_err(TemplateError::ValidationFailed),
+            None => Ok(params.clone()),
+        }
+    }
+
+    /// Compiler settings from the template's manifest; empty if it has none.
+    pub fn compiler_settings(&self, template_name: &str) -> CompilerSettings {
+        self.schemas.get(template_name)
+            .map(|schema| schema.compiler_settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Name of the contract to deploy from `template_name`: the manifest's `main_contract`,
+    /// or else the template name without `.sol.tera`.
+    pub fn main_contract(&self, template_name: &str) -> String {
+        self.schemas.get(template_name)
+            .and_then(|schema| schema.main_contract.clone())
+            .unwrap_or_else(|| template_name.trim_end_matches(".sol.tera").to_string())
+    }
+
+    /// Lists every loaded template with its manifest, sorted by name.
+    /// Templates without a manifest are listed with an empty schema.
+    pub fn list_templates(&self) -> Vec<TemplateInfo> {
+        let mut templates: Vec<TemplateInfo> = self.tera.get_template_names()
+            .map(|name| TemplateInfo {
+                name: name.to_string(),
+                schema: self.schemas.get(name).cloned().unwrap_or_default(),
+            })
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+
+    /// Returns the catalog entry for `template_name` together with its raw source, as it
+    /// was when the templates were loaded.
+    pub fn template_detail(&self, template_name: &str) -> Result<TemplateDetail, TemplateError> {
+        let info = self.list_templates().into_iter()
+            .find(|info| info.name == template_name)
+            .ok_or_else(|| TemplateError::TemplateNotFound(template_name.to_string()))?;
+        let source = self.sources.get(template_name).cloned().unwrap_or_default();
+        let imports = import_paths(&source);
+        Ok(TemplateDetail { info, source, imports })
     }
 
     /// Renders a Solidity contract template.
     /// `template_name` should be the filename, e.g., "TokenVesting.sol.tera".
     /// `params` should be a serializable struct or a `serde_json::Value` that can be converted to `tera::Context`.
+    /// They are validated against the template's manifest first, see [`Self::validate_params`].
     pub fn render_template<S: Serialize>(
         &self,
         template_name: &str,
         params: &S,
     ) -> Result<String, TemplateError> {
+        let params = serde_json::to_value(params)
+            .map_err(|e| TemplateError::ValidationFailed(vec![ValidationIssue {
+                field: String::new(),
+                message: e.to_string(),
+            }]))?;
+        let params = self.validate_params(template_name, &params)?;
+        self.render_checked(template_name, &params)
+    }
+
+    /// Validates `params` like [`Self::render_template`] and renders the template with
+    /// them, returning the validated params, with defaults applied, alongside the source.
+    pub fn validate_and_render(&self, template_name: &str, params: &Value) -> Result<(Value, String), TemplateError> {
+        let params = self.validate_params(template_name, params)?;
+        let source = self.render_checked(template_name, &params)?;
+        Ok((params, source))
+    }
+
+    /// Renders `template_name` with params that were already validated.
+    fn render_checked(&self, template_name: &str, params: &Value) -> Result<String, TemplateError> {
         let context = Context::from_serialize(params)
             .map_err(TemplateError::TeraError)?;
 
-        if self.tera.get_template_names().find(|&name| name == template_name).is_none() {
-            return Err(TemplateError::TemplateNotFound(template_name.to_string()));
-        }
-
         self.tera.render(template_name, &context)
             .map_err(TemplateError::TeraError)
     }
 }
 
+/// Extracts the paths of `import` directives from a template's source.
+/// The raw template may contain Tera syntax, so this scans lines instead of parsing Solidity.
+fn import_paths(source: &str) -> Vec<String> {
+    source.lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with("import ") || line.starts_with("import\""))
+        .filter_map(|line| {
+            let quote = line.find(['"', '\''])?;
+            let delimiter = line[quote..].chars().next()?;
+            let rest = &line[quote + 1..];
+            rest.find(delimiter).map(|end| rest[..end].to_string())
+        })
+        .collect()
+}
+
 // Example usage (will be integrated into main.rs later)
 /*
 fn example() {
@@ -96,3 +239,75 @@ fn example() {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn engine() -> SolTemplateEngine {
+        SolTemplateEngine::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../contracts")).unwrap()
+    }
+
+    #[test]
+    fn test_render_rejects_invalid_params() {
+        let result = engine().render_template("TokenVesting.sol.tera", &json!({ "beneficiary": "0x12" }));
+        match result {
+            Err(TemplateError::ValidationFailed(issues)) => {
+                assert!(issues.iter().any(|i| i.field == "beneficiary"));
+                assert!(issues.iter().any(|i| i.field == "token_address"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_render_valid_params() {
+        let rendered = engine().render_template("TokenVesting.sol.tera", &json!({
+            "token_address": "0x1111111111111111111111111111111111111111",
+            "beneficiary": "0x2222222222222222222222222222222222222222",
+            "start_time": 1700000000,
+            "duration": 31536000,
+            "initial_owner": "0x3333333333333333333333333333333333333333",
+        })).unwrap();
+        assert!(rendered.contains("// Cliff Duration: 0"));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let engine = engine();
+        let templates = engine.list_templates();
+        let vesting = templates.iter().find(|t| t.name == "TokenVesting.sol.tera").unwrap();
+        assert_eq!(vesting.schema.category.as_deref(), Some("vesting"));
+        assert_eq!(vesting.schema.parameters.len(), 6);
+        assert_eq!(engine.main_contract("TokenVesting.sol.tera"), "TokenVesting");
+
+        let detail = engine.template_detail("TokenVesting.sol.tera").unwrap();
+        assert!(detail.source.contains("contract TokenVesting"));
+        assert_eq!(detail.imports, vec![
+            "@openzeppelin/contracts/token/ERC20/IERC20.sol",
+            "@openzeppelin/contracts/access/Ownable.sol",
+            "@openzeppelin/contracts/utils/ReentrancyGuard.sol",
+        ]);
+        assert!(matches!(engine.template_detail("Missing.sol.tera"), Err(TemplateError::TemplateNotFound(_))));
+    }
+
+    #[test]
+    fn test_detail_returns_the_loaded_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Plain.sol.tera");
+        fs::write(&path, "import \"./A.sol\";\ncontract Plain {}\n").unwrap();
+        let engine = SolTemplateEngine::new(dir.path().to_path_buf()).unwrap();
+        fs::write(&path, "contract Edited {}\n").unwrap();
+
+        let detail = engine.template_detail("Plain.sol.tera").unwrap();
+        assert!(detail.source.contains("contract Plain"));
+        assert_eq!(detail.imports, vec!["./A.sol"]);
+    }
+
+    #[test]
+    fn test_import_paths() {
+        let source = "import {IERC20} from './IERC20.sol';\nimport * as Lib from \"lib/Lib.sol\";\n// import \"no.sol\";";
+        assert_eq!(import_paths(source), vec!["./IERC20.sol", "lib/Lib.sol"]);
+    }
+}
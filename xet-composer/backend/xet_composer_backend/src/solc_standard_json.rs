@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Input document for `solc --standard-json`.
+#[derive(Debug, Serialize)]
+pub struct StandardJsonInput {
+    pub language: String,
+    pub sources: BTreeMap<String, SourceContent>,
+    pub settings: Settings,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceContent {
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub optimizer: Optimizer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm_version: Option<EvmVersion>,
+    #[serde(rename = "viaIR", skip_serializing_if = "Option::is_none")]
+    pub via_ir: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<DebugSettings>,
+    pub remappings: Vec<String>,
+    /// file -> contract -> requested outputs; `*` selects everything.
+    pub output_selection: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Optimizer {
+    pub enabled: bool,
+    pub runs: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataSettings {
+    pub bytecode_hash: MetadataHash,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSettings {
+    pub revert_strings: RevertStrings,
+}
+
+/// Target EVM version. Chains without PUSH0 need `paris` or older.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvmVersion {
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+    Prague,
+}
+
+/// Hash solc appends to the runtime bytecode as a pointer to the contract metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataHash {
+    Ipfs,
+    Bzzr1,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RevertStrings {
+    Default,
+    Strip,
+    Debug,
+    VerboseDebug,
+}
+
+/// Compiler options, as set in the config, a template manifest or a request.
+///
+/// Unset fields fall through to the next layer, see [`CompilerSettings::overlay`]; what is
+/// still unset after the last layer is left to solc's own defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompilerSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimizer: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs: Option<u32>,
+    #[serde(alias = "evmVersion", skip_serializing_if = "Option::is_none")]
+    pub evm_version: Option<EvmVersion>,
+    #[serde(alias = "viaIR", skip_serializing_if = "Option::is_none")]
+    pub via_ir: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_hash: Option<MetadataHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_strings: Option<RevertStrings>,
+}
+
+impl CompilerSettings {
+    /// Optimizer enabled with 200 runs, solc's defaults otherwise.
+    pub fn defaults() -> Self {
+        Self { optimizer: Some(true), runs: Some(200), ..Self::default() }
+    }
+
+    /// `self` with every field that is set in `other` replaced by `other`'s value.
+    pub fn overlay(&self, other: &CompilerSettings) -> CompilerSettings {
+        CompilerSettings {
+            optimizer: other.optimizer.or(self.optimizer),
+            runs: other.runs.or(self.runs),
+            evm_version: other.evm_version.or(self.evm_version),
+            via_ir: other.via_ir.or(self.via_ir),
+            metadata_hash: other.metadata_hash.or(self.metadata_hash),
+            revert_strings: other.revert_strings.or(self.revert_strings),
+        }
+    }
+}
+
+impl StandardJsonInput {
+    /// Single-source input requesting, for every contract, the ABI, metadata, storage layout,
+    /// method identifiers, the creation and runtime bytecode with their source maps and the
+    /// creation code's library link references,
+    /// plus the AST of every source to tell contracts, interfaces and libraries apart.
+    pub fn new(source_name: &str, content: &str, remappings: &[String], compiler: &CompilerSettings) -> Self {
+        let mut sources = BTreeMap::new();
+        sources.insert(source_name.to_string(), SourceContent { content: content.to_string() });
+
+        let mut contract_outputs = BTreeMap::new();
+        contract_outputs.insert("*".to_string(), [
+            "abi",
+            "metadata",
+            "storageLayout",
+            "evm.methodIdentifiers",
+            "evm.bytecode.object",
+            "evm.bytecode.sourceMap",
+            "evm.bytecode.linkReferences",
+            "evm.deployedBytecode.object",
+            "evm.deployedBytecode.sourceMap",
+            "evm.deployedBytecode.immutableReferences",
+        ].iter().map(|output| output.to_string()).collect());
+        contract_outputs.insert(String::new(), vec!["ast".to_string()]);
+        let mut output_selection = BTreeMap::new();
+        output_selection.insert("*".to_string(), contract_outputs);
+
+        Self {
+            language: "Solidity".to_string(),
+            sources,
+            settings: Settings {
+                optimizer: Optimizer {
+                    enabled: compiler.optimizer.unwrap_or(false),
+                    runs: compiler.runs.unwrap_or(200),
+                },
+                evm_version: compiler.evm_version,
+                via_ir: compiler.via_ir,
+                metadata: compiler.metadata_hash.map(|bytecode_hash| MetadataSettings { bytecode_hash }),
+                debug: compiler.revert_strings.map(|revert_strings| DebugSettings { revert_strings }),
+                remappings: remappings.to_vec(),
+                output_selection,
+            },
+        }
+    }
+}
+
+/// Output document of `solc --standard-json`. Only the fields we consume are modelled.
+#[derive(Debug, Deserialize)]
+pub struct StandardJsonOutput {
+    #[serde(default)]
+    pub errors: Vec<SolcMessage>,
+    /// file -> contract name -> output
+    #[serde(default)]
+    pub contracts: BTreeMap<String, BTreeMap<String, ContractOutput>>,
+    #[serde(default)]
+    pub sources: BTreeMap<String, SourceOutput>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SourceOutput {
+    #[serde(default)]
+    pub ast: Value,
+}
+
+/// What a `contract`, `interface` or `library` definition declares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContractKind {
+    #[default]
+    Contract,
+    Abstract,
+    Interface,
+    Library,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractOutput {
+    #[serde(default)]
+    pub abi: Value,
+    /// The metadata JSON, as a string; its hash is embedded in the runtime bytecode.
+    #[serde(default)]
+    pub metadata: String,
+    #[serde(default)]
+    pub storage_layout: Value,
+    #[serde(default)]
+    pub evm: EvmOutput,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmOutput {
+    #[serde(default)]
+    pub bytecode: BytecodeOutput,
+    #[serde(default)]
+    pub deployed_bytecode: BytecodeOutput,
+    /// Function signature -> 4-byte selector in hex, e.g. `"release()": "86d1a69f"`.
+    #[serde(default)]
+    pub method_identifiers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BytecodeOutput {
+    #[serde(default)]
+    pub object: String,
+    #[serde(default)]
+    pub source_map: String,
+    /// AST id of each immutable -> the runtime bytecode ranges it is written to.
+    #[serde(default)]
+    pub immutable_references: BTreeMap<String, Vec<BytecodeRange>>,
+    #[serde(default)]
+    pub link_references: LinkReferences,
+}
+
+/// Source file -> library name -> the bytecode ranges holding a `__$...$__` placeholder
+/// for the library's address.
+pub type LinkReferences = BTreeMap<String, BTreeMap<String, Vec<BytecodeRange>>>;
+
+/// A range of bytecode, in bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BytecodeRange {
+    pub start: usize,
+    pub length: usize,
+}
+
+/// Raw entry of the `errors` array; solc reports warnings and infos here too.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolcMessage {
+    pub severity: String,
+    #[serde(default)]
+    pub error_code: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub message: String,
+    #[serde(default)]
+    pub formatted_message: Option<String>,
+    #[serde(default)]
+    pub source_location: Option<RawSourceLocation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RawSourceLocation {
+    pub file: String,
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// A compiler error, warning or info with its location resolved to line and column.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error_code: Option<String>,
+    /// solc's error type, e.g. `ParserError`, `TypeError` or `Warning`.
+    pub kind: String,
+    pub message: String,
+    pub formatted_message: Option<String>,
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line and column; only resolved for locations inside the rendered source.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl StandardJsonOutput {
+    /// Kind of the contract `name` defined in `source_name`, read from the source's AST.
+    /// Contracts the AST does not describe are reported as plain contracts.
+    pub fn contract_kind(&self, source_name: &str, name: &str) -> ContractKind {
+        let Some(nodes) = self.sources.get(source_name).and_then(|source| source.ast["nodes"].as_array()) else {
+            return ContractKind::Contract;
+        };
+        let definition = nodes.iter().find(|node| node["nodeType"] == "ContractDefinition" && node["name"] == name);
+        match definition {
+            Some(node) if node["contractKind"] == "library" => ContractKind::Library,
+            Some(node) if node["contractKind"] == "interface" => ContractKind::Interface,
+            Some(node) if node["abstract"] == true => ContractKind::Abstract,
+            _ => ContractKind::Contract,
+        }
+    }
+
+    /// Converts solc's messages into diagnostics, resolving offsets in `source_name`
+    /// against `source`.
+    pub fn diagnostics(&self, source_name: &str, source: &str) -> Vec<Diagnostic> {
+        self.errors.iter().map(|msg| {
+            let severity = match msg.severity.as_str() {
+                "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => Severity::Info,
+            };
+            // solc uses -1 offsets for locations it cannot attribute.
+            let location = msg.source_location.as_ref()
+                .filter(|loc| loc.start >= 0 && loc.end >= 0)
+                .map(|loc| {
+                    let start = loc.start as usize;
+                    let (line, column) = if loc.file == source_name {
+                        line_and_column(source, start).unzip()
+                    } else {
+                        (None, None)
+                    };
+                    SourceLocation { file: loc.file.clone(), start, end: loc.end as usize, line, column }
+                });
+            Diagnostic {
+                severity,
+                error_code: msg.error_code.clone(),
+                kind: msg.kind.clone(),
+                message: msg.message.clone(),
+                formatted_message: msg.formatted_message.clone(),
+                location,
+            }
+        }).collect()
+    }
+}
+
+/// Maps a byte offset to a 1-based (line, column), counting columns in characters.
+pub(crate) fn line_and_column(source: &str, offset: usize) -> Option<(usize, usize)> {
+    let prefix = source.get(..offset)?;
+    let line = prefix.matches('\n').count() + 1;
+    let line_start = prefix.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Some((line, prefix[line_start..].chars().count() + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_overlay_and_wire_format() {
+        let manifest: CompilerSettings = serde_json::from_value(serde_json::json!({
+            "evm_version": "paris",
+            "via_ir": true,
+            "runs": 1000
+        })).unwrap();
+        let request: CompilerSettings = serde_json::from_value(serde_json::json!({
+            "viaIR": false,
+            "metadata_hash": "none",
+            "revert_strings": "strip"
+        })).unwrap();
+        let settings = CompilerSettings::defaults().overlay(&manifest).overlay(&request);
+        assert_eq!(settings.optimizer, Some(true));
+        assert_eq!(settings.runs, Some(1000));
+        assert_eq!(settings.via_ir, Some(false));
+
+        let input = serde_json::to_value(StandardJsonInput::new("A.sol", "", &[], &settings)).unwrap();
+        assert_eq!(input["settings"]["optimizer"], serde_json::json!({ "enabled": true, "runs": 1000 }));
+        assert_eq!(input["settings"]["evmVersion"], "paris");
+        assert_eq!(input["settings"]["viaIR"], false);
+        assert_eq!(input["settings"]["metadata"]["bytecodeHash"], "none");
+        assert_eq!(input["settings"]["debug"]["revertStrings"], "strip");
+
+        let input = serde_json::to_value(StandardJsonInput::new("A.sol", "", &[], &CompilerSettings::defaults())).unwrap();
+        assert!(input["settings"].get("evmVersion").is_none());
+        assert!(serde_json::from_value::<CompilerSettings>(serde_json::json!({ "evm_version": "frontier" })).is_err());
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        let source = "pragma solidity ^0.8.0;\ncontract A {\n    uint x\n}\n";
+        assert_eq!(line_and_column(source, 0), Some((1, 1)));
+        assert_eq!(line_and_column(source, source.find("uint").unwrap()), Some((3, 5)));
+        assert_eq!(line_and_column(source, source.len() + 1), None);
+    }
+
+    #[test]
+    fn test_diagnostics_from_output() {
+        let output: StandardJsonOutput = serde_json::from_value(serde_json::json!({
+            "errors": [
+                {
+                    "component": "general",
+                    "errorCode": "2072",
+                    "formattedMessage": "Warning: Unused local variable.",
+                    "message": "Unused local variable.",
+                    "severity": "warning",
+                    "sourceLocation": { "end": 30, "file": "A.sol", "start": 25 },
+                    "type": "Warning"
+                },
+                {
+                    "component": "general",
+                    "formattedMessage": "Error: Source \"lib/X.sol\" not found",
+                    "message": "Source \"lib/X.sol\" not found",
+                    "severity": "error",
+                    "sourceLocation": { "end": 10, "file": "lib/X.sol", "start": 0 },
+                    "type": "ParserError"
+                }
+            ]
+        })).unwrap();
+        let diagnostics = output.diagnostics("A.sol", "pragma solidity ^0.8.0;\ncontract A {}\n");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].error_code.as_deref(), Some("2072"));
+        let location = diagnostics[0].location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (Some(2), Some(2)));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].location.as_ref().unwrap().line, None);
+    }
+}
@@ -0,0 +1,249 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solang_parser::pt::{SourceUnitPart, StringLiteral};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Manifest of the compiler cache, in the format of binaries.soliditylang.org's `list.json`.
+pub const MANIFEST_FILE: &str = "list.json";
+
+/// A `pragma solidity` version range, e.g. `^0.8.19` or `>=0.7.0 <0.9.0 || ^0.6.12`.
+#[derive(Debug, Clone)]
+pub struct SolcRequirement {
+    pub raw: String,
+    alternatives: Vec<VersionReq>, // Any one may match; `||` in the pragma
+}
+
+impl SolcRequirement {
+    /// Translates a Solidity version range into semver requirements. Solidity separates
+    /// comparators with spaces and treats a bare version as an exact match, while the
+    /// semver crate wants commas and reads a bare version as a caret range.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut alternatives = Vec::new();
+        for alternative in raw.split("||") {
+            let mut comparators: Vec<String> = Vec::new();
+            let mut pending_op = String::new();
+            for token in alternative.split_whitespace() {
+                // `>= 0.8.0` is valid Solidity; join the operator with its version.
+                if token.chars().all(|c| "<>=^~".contains(c)) {
+                    pending_op.push_str(token);
+                    continue;
+                }
+                let token = format!("{}{}", std::mem::take(&mut pending_op), token);
+                if token.starts_with(|c: char| c.is_ascii_digit()) {
+                    comparators.push(format!("={}", token));
+                } else {
+                    comparators.push(token);
+                }
+            }
+            if !pending_op.is_empty() || comparators.is_empty() {
+                return Err(format!("invalid version range '{}'", raw.trim()));
+            }
+            let req = VersionReq::parse(&comparators.join(", "))
+                .map_err(|e| format!("invalid version range '{}': {}", raw.trim(), e))?;
+            alternatives.push(req);
+        }
+        Ok(Self { raw: raw.trim().to_string(), alternatives })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.alternatives.iter().any(|req| req.matches(version))
+    }
+}
+
+/// Collects the `pragma solidity` ranges of `source`. A file may carry several, and a
+/// compiler must satisfy all of them.
+///
+/// Returns `Ok(None)` if solang-parser cannot parse the source; solc then gets to report
+/// the syntax errors itself.
+pub fn pragma_requirements(source: &str) -> Result<Option<Vec<SolcRequirement>>, String> {
+    let (unit, _comments) = match solang_parser::parse(source, 0) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok(None),
+    };
+    let mut requirements = Vec::new();
+    for part in &unit.0 {
+        if let SourceUnitPart::PragmaDirective(_, Some(name), Some(StringLiteral { string, .. })) = part {
+            if name.name == "solidity" {
+                requirements.push(SolcRequirement::parse(string)?);
+            }
+        }
+    }
+    Ok(Some(requirements))
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    builds: Vec<ManifestBuild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestBuild {
+    path: String,
+    version: String,
+    sha256: String,
+}
+
+/// A compiler listed in the cache manifest whose binary is present on disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledCompiler {
+    pub version: Version,
+    pub path: PathBuf,
+    pub sha256: String, // Expected digest from the manifest, lowercase hex without `0x`
+    /// Whether the binary's SHA-256 matches the manifest. Unverified binaries are never run.
+    pub verified: bool,
+}
+
+/// Local directory of solc binaries described by a `list.json` manifest.
+pub struct CompilerCache {
+    dir: PathBuf,
+    // Digests keyed by path, reused while the file's size and mtime are unchanged.
+    digests: Mutex<HashMap<PathBuf, (u64, SystemTime, String)>>,
+}
+
+impl CompilerCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, digests: Mutex::new(HashMap::new()) }
+    }
+
+    /// Lists the compilers from the manifest that are present on disk, newest first,
+    /// checking each binary's checksum.
+    pub fn installed(&self) -> Result<Vec<InstalledCompiler>, String> {
+        let manifest_path = self.dir.join(MANIFEST_FILE);
+        let raw = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("could not read {}: {}", manifest_path.display(), e))?;
+        let manifest: Manifest = serde_json::from_str(&raw)
+            .map_err(|e| format!("could not parse {}: {}", manifest_path.display(), e))?;
+
+        let mut compilers = Vec::new();
+        for build in manifest.builds {
+            let path = self.dir.join(&build.path);
+            if !path.is_file() {
+                continue;
+            }
+            let version = Version::parse(&build.version)
+                .map_err(|e| format!("{}: invalid version '{}': {}", manifest_path.display(), build.version, e))?;
+            let sha256 = build.sha256.trim_start_matches("0x").to_ascii_lowercase();
+            let verified = match self.digest(&path) {
+                Ok(actual) => actual == sha256,
+                Err(e) => {
+                    eprintln!("Could not hash {}: {}", path.display(), e);
+                    false
+                }
+            };
+            if !verified {
+                eprintln!("Checksum mismatch for solc {} at {}; it will not be used.", version, path.display());
+            }
+            compilers.push(InstalledCompiler { version, path, sha256, verified });
+        }
+        compilers.sort_by(|a, b| b.version.cmp(&a.version));
+        Ok(compilers)
+    }
+
+    /// The newest verified compiler satisfying every requirement.
+    pub fn select(&self, requirements: &[SolcRequirement]) -> Result<InstalledCompiler, String> {
+        let installed = self.installed()?;
+        installed.iter()
+            .filter(|c| c.verified)
+            .find(|c| requirements.iter().all(|req| req.matches(&c.version)))
+            .cloned()
+            .ok_or_else(|| {
+                let available: Vec<String> = installed.iter()
+                    .map(|c| if c.verified { c.version.to_string() } else { format!("{} (checksum mismatch)", c.version) })
+                    .collect();
+                format!(
+                    "no installed solc satisfies {} (installed: {})",
+                    requirements.iter().map(|r| r.raw.as_str()).collect::<Vec<_>>().join(" and "),
+                    if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                )
+            })
+    }
+
+    fn digest(&self, path: &Path) -> std::io::Result<String> {
+        let metadata = fs::metadata(path)?;
+        let key = (metadata.len(), metadata.modified()?);
+        if let Some((len, modified, digest)) = self.digests.lock().unwrap().get(path) {
+            if (*len, *modified) == key {
+                return Ok(digest.clone());
+            }
+        }
+        let digest = hex::encode(Sha256::digest(fs::read(path)?));
+        self.digests.lock().unwrap().insert(path.to_path_buf(), (key.0, key.1, digest.clone()));
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Writes a fake compiler cache. Each binary is a shell script printing `output`
+    /// with `{version}` replaced by its own version.
+    #[cfg(unix)]
+    pub(crate) fn fake_compiler_cache(dir: &Path, versions: &[&str], output: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let mut builds = Vec::new();
+        for version in versions {
+            let file = format!("solc-v{}", version);
+            let script = format!("#!/bin/sh\ncat > /dev/null\ncat <<'EOF'\n{}\nEOF\n", output.replace("{version}", version));
+            fs::write(dir.join(&file), &script).unwrap();
+            fs::set_permissions(dir.join(&file), fs::Permissions::from_mode(0o755)).unwrap();
+            builds.push(serde_json::json!({
+                "path": file,
+                "version": version,
+                "sha256": format!("0x{}", hex::encode(Sha256::digest(script.as_bytes()))),
+            }));
+        }
+        fs::write(dir.join(MANIFEST_FILE), serde_json::json!({ "builds": builds }).to_string()).unwrap();
+    }
+
+    fn requirement(raw: &str) -> SolcRequirement {
+        SolcRequirement::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn test_requirement_syntax() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        assert!(requirement("^0.8.19").matches(&v("0.8.24")));
+        assert!(!requirement("^0.8.19").matches(&v("0.9.0")));
+        assert!(requirement("0.8.19").matches(&v("0.8.19")));
+        assert!(!requirement("0.8.19").matches(&v("0.8.20")));
+        assert!(requirement(">=0.7.0 <0.8.0").matches(&v("0.7.6")));
+        assert!(requirement(">= 0.7.0 < 0.8.0").matches(&v("0.7.6")));
+        assert!(requirement("^0.6.12 || ^0.8.0").matches(&v("0.6.12")));
+        assert!(SolcRequirement::parse(">=").is_err());
+        assert!(SolcRequirement::parse("^banana").is_err());
+    }
+
+    #[test]
+    fn test_pragma_requirements() {
+        let source = "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.19;\npragma abicoder v2;\ncontract A {}\n";
+        let requirements = pragma_requirements(source).unwrap().unwrap();
+        assert_eq!(requirements.len(), 1);
+        assert_eq!(requirements[0].raw, "^0.8.19");
+        assert!(pragma_requirements("contract {").unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_select_newest_verified_match() {
+        let dir = tempfile::tempdir().unwrap();
+        fake_compiler_cache(dir.path(), &["0.7.6", "0.8.19", "0.8.24"], "{}");
+        let cache = CompilerCache::new(dir.path().to_path_buf());
+
+        assert_eq!(cache.select(&[requirement("^0.8.0")]).unwrap().version, Version::new(0, 8, 24));
+        assert_eq!(cache.select(&[requirement("^0.8.0"), requirement("<0.8.20")]).unwrap().version, Version::new(0, 8, 19));
+        let err = cache.select(&[requirement("^0.6.0")]).unwrap_err();
+        assert!(err.contains("^0.6.0") && err.contains("0.8.24, 0.8.19, 0.7.6"), "{}", err);
+
+        // A tampered binary is listed but never selected.
+        fs::write(dir.path().join("solc-v0.8.24"), "#!/bin/sh\necho tampered\n").unwrap();
+        let installed = cache.installed().unwrap();
+        assert!(!installed[0].verified);
+        assert_eq!(cache.select(&[requirement("^0.8.0")]).unwrap().version, Version::new(0, 8, 19));
+    }
+}
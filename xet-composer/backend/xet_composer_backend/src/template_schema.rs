@@ -0,0 +1,425 @@
+use ethers::abi::param_type::Reader;
+use ethers::abi::ParamType;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+
+use crate::abi_value::{json_to_token, parse_int, parse_uint};
+use crate::address::parse_address;
+use crate::solc_standard_json::CompilerSettings;
+
+/// Parameter manifest that sits next to a template, e.g. `TokenVesting.schema.json`
+/// for `TokenVesting.sol.tera`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateSchema {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// The contract to deploy when the template defines several contracts, interfaces
+    /// or libraries. Defaults to the template name, e.g. `TokenVesting`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_contract: Option<String>,
+    #[serde(default)]
+    pub parameters: Vec<ParamSpec>,
+    #[serde(default)]
+    pub rules: Vec<CrossFieldRule>,
+    /// Compiler settings this template needs, e.g. `"evm_version": "paris"` or `"via_ir": true`.
+    /// Requests can override them field by field.
+    #[serde(default)]
+    pub compiler_settings: CompilerSettings,
+}
+
+/// Declaration of a single template parameter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParamSpec {
+    pub name: String,
+    /// Solidity type, e.g. `address`, `uint256`, `bytes32` or `address[]`.
+    #[serde(rename = "type")]
+    pub solidity_type: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    /// Inclusive bounds for integer types, as a JSON number or decimal string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+    /// For `address` and `address[]` types: reject the zero address.
+    #[serde(default)]
+    pub nonzero: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Comparison between two integer parameters, e.g. `cliff_duration <= duration`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossFieldRule {
+    pub left: String,
+    pub op: String,
+    pub right: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A single problem found while validating params against a [`TemplateSchema`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    /// Path of the offending value, e.g. `beneficiary` or `recipients[2]`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+impl TemplateSchema {
+    /// Reads and checks a manifest file. Errors are returned as plain messages.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let schema: TemplateSchema =
+            serde_json::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?;
+        schema.check().map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(schema)
+    }
+
+    /// Checks that the manifest itself is consistent: known types, unique names, integer
+    /// bounds on integer parameters only, defaults that pass their own spec, and rules
+    /// that only reference declared integer parameters.
+    fn check(&self) -> Result<(), String> {
+        for (i, spec) in self.parameters.iter().enumerate() {
+            if self.parameters[..i].iter().any(|other| other.name == spec.name) {
+                return Err(format!("parameter '{}' is declared twice", spec.name));
+            }
+            let kind = Reader::read(&spec.solidity_type)
+                .map_err(|_| format!("parameter '{}' has unknown type '{}'", spec.name, spec.solidity_type))?;
+            if spec.nonzero && !is_address_type(&kind) {
+                return Err(format!("parameter '{}' is marked nonzero but is not an address", spec.name));
+            }
+            for (label, bound) in [("min", &spec.min), ("max", &spec.max)] {
+                let Some(bound) = bound else {
+                    continue;
+                };
+                if !matches!(kind, ParamType::Uint(_) | ParamType::Int(_)) {
+                    return Err(format!("parameter '{}' has a {} but is not an integer", spec.name, label));
+                }
+                if integer(bound).is_none() {
+                    return Err(format!("parameter '{}' has {} {}, which is not an integer", spec.name, label, bound));
+                }
+            }
+            if let (Some(min), Some(max)) = (&spec.min, &spec.max) {
+                if compare_integers(min, max) == Some(Ordering::Greater) {
+                    return Err(format!("parameter '{}' has a min above its max", spec.name));
+                }
+            }
+            if let Some(default) = &spec.default {
+                check_value(spec, default)
+                    .map_err(|issue| format!("default of parameter '{}' is invalid: {}", spec.name, issue.message))?;
+            }
+        }
+        for rule in &self.rules {
+            if comparison(&rule.op).is_none() {
+                return Err(format!("rule '{} {} {}' has unknown operator", rule.left, rule.op, rule.right));
+            }
+            for name in [&rule.left, &rule.right] {
+                match self.param(name).map(|spec| Reader::read(&spec.solidity_type)) {
+                    Some(Ok(ParamType::Uint(_))) | Some(Ok(ParamType::Int(_))) => {}
+                    Some(_) => return Err(format!("rule references non-integer parameter '{}'", name)),
+                    None => return Err(format!("rule references undeclared parameter '{}'", name)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn param(&self, name: &str) -> Option<&ParamSpec> {
+        self.parameters.iter().find(|spec| spec.name == name)
+    }
+
+    /// Validates `params` and returns them with defaults filled in.
+    /// Every violation is collected rather than stopping at the first one.
+    pub fn validate(&self, params: &Value) -> Result<Value, Vec<ValidationIssue>> {
+        let empty = Map::new();
+        let input = match params {
+            Value::Object(map) => map,
+            Value::Null => &empty,
+            other => return Err(vec![ValidationIssue::new("", format!("expected a JSON object, got {}", other))]),
+        };
+
+        let mut issues = Vec::new();
+        let mut output = Map::new();
+
+        for name in input.keys() {
+            if self.param(name).is_none() {
+                issues.push(ValidationIssue::new(name.clone(), "unknown parameter"));
+            }
+        }
+
+        for spec in &self.parameters {
+            let value = match input.get(&spec.name).filter(|v| !v.is_null()) {
+                Some(value) => value,
+                None => match &spec.default {
+                    Some(default) => default,
+                    None if spec.required => {
+                        issues.push(ValidationIssue::new(spec.name.clone(), "required parameter is missing"));
+                        continue;
+                    }
+                    None => continue,
+                },
+            };
+            match check_value(spec, value) {
+                Ok(()) => {
+                    output.insert(spec.name.clone(), value.clone());
+                }
+                Err(issue) => issues.push(issue),
+            }
+        }
+
+        for rule in &self.rules {
+            let (Some(left), Some(right)) = (output.get(&rule.left), output.get(&rule.right)) else {
+                continue;
+            };
+            let holds = compare_integers(left, right)
+                .zip(comparison(&rule.op))
+                .map(|(ordering, allowed)| allowed.contains(&ordering))
+                .unwrap_or(false);
+            if !holds {
+                let message = rule.message.clone()
+                    .unwrap_or_else(|| format!("must satisfy {} {} {}", rule.left, rule.op, rule.right));
+                issues.push(ValidationIssue::new(rule.left.clone(), message));
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(Value::Object(output))
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// Type-checks `value` against the parameter's Solidity type and bounds.
+fn check_value(spec: &ParamSpec, value: &Value) -> Result<(), ValidationIssue> {
+    let kind = Reader::read(&spec.solidity_type)
+        .map_err(|_| ValidationIssue::new(spec.name.clone(), format!("unknown type '{}'", spec.solidity_type)))?;
+    json_to_token(value, &kind, &[], &spec.name).map_err(|e| ValidationIssue::new(e.path, e.reason))?;
+
+    if spec.nonzero {
+        check_nonzero_addresses(value, &kind, &spec.name)?;
+    }
+    if let Some(min) = &spec.min {
+        if compare_integers(value, min) == Some(Ordering::Less) {
+            return Err(ValidationIssue::new(spec.name.clone(), format!("must be >= {}", display_bound(min))));
+        }
+    }
+    if let Some(max) = &spec.max {
+        if compare_integers(value, max) == Some(Ordering::Greater) {
+            return Err(ValidationIssue::new(spec.name.clone(), format!("must be <= {}", display_bound(max))));
+        }
+    }
+    Ok(())
+}
+
+fn is_address_type(kind: &ParamType) -> bool {
+    match kind {
+        ParamType::Address => true,
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => is_address_type(inner),
+        _ => false,
+    }
+}
+
+/// Rejects the zero address in an already type-checked address or address array value.
+fn check_nonzero_addresses(value: &Value, kind: &ParamType, path: &str) -> Result<(), ValidationIssue> {
+    match (kind, value) {
+        (ParamType::Address, Value::String(s)) => parse_address(s, false)
+            .map(|_| ())
+            .map_err(|e| ValidationIssue::new(path, e.to_string())),
+        (ParamType::Array(inner) | ParamType::FixedArray(inner, _), Value::Array(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                check_nonzero_addresses(item, inner, &format!("{}[{}]", path, i))?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn display_bound(bound: &Value) -> String {
+    bound.as_str().map(str::to_string).unwrap_or_else(|| bound.to_string())
+}
+
+/// Orderings accepted by a rule operator.
+fn comparison(op: &str) -> Option<&'static [Ordering]> {
+    match op {
+        "<" => Some(&[Ordering::Less]),
+        "<=" => Some(&[Ordering::Less, Ordering::Equal]),
+        ">" => Some(&[Ordering::Greater]),
+        ">=" => Some(&[Ordering::Greater, Ordering::Equal]),
+        "==" => Some(&[Ordering::Equal]),
+        "!=" => Some(&[Ordering::Less, Ordering::Greater]),
+        _ => None,
+    }
+}
+
+/// A JSON integer (number, decimal or hex string) as its sign and magnitude, or `None`
+/// if `value` is not an integer.
+fn integer(value: &Value) -> Option<(bool, U256)> {
+    match parse_uint(value) {
+        Ok(n) => Some((false, n)),
+        Err(_) => parse_int(value).ok().map(|n| (n.is_negative(), n.unsigned_abs())),
+    }
+}
+
+/// Compares two JSON integers of any sign. Returns `None` if either side is not an integer.
+fn compare_integers(a: &Value, b: &Value) -> Option<Ordering> {
+    let (a_neg, a_mag) = integer(a)?;
+    let (b_neg, b_mag) = integer(b)?;
+    Some(match (a_neg, b_neg) {
+        (false, false) => a_mag.cmp(&b_mag),
+        (true, true) => b_mag.cmp(&a_mag),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vesting_schema() -> TemplateSchema {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../contracts/TokenVesting.schema.json");
+        TemplateSchema::load(&path).unwrap()
+    }
+
+    fn vesting_params() -> Value {
+        json!({
+            "token_address": "0x1111111111111111111111111111111111111111",
+            "beneficiary": "0x2222222222222222222222222222222222222222",
+            "start_time": 1700000000,
+            "cliff_duration": 86400,
+            "duration": "31536000",
+            "initial_owner": "0x3333333333333333333333333333333333333333",
+        })
+    }
+
+    #[test]
+    fn test_valid_params_pass() {
+        let validated = vesting_schema().validate(&vesting_params()).unwrap();
+        assert_eq!(validated["duration"], json!("31536000"));
+    }
+
+    #[test]
+    fn test_default_is_applied() {
+        let mut params = vesting_params();
+        params.as_object_mut().unwrap().remove("cliff_duration");
+        let validated = vesting_schema().validate(&params).unwrap();
+        assert_eq!(validated["cliff_duration"], json!(0));
+    }
+
+    #[test]
+    fn test_all_violations_are_reported() {
+        let mut params = vesting_params();
+        params.as_object_mut().unwrap().remove("beneficiary");
+        params["token_address"] = json!("0x12");
+        params["duration"] = json!(0);
+        params["extra"] = json!(true);
+
+        let issues = vesting_schema().validate(&params).unwrap_err();
+        let fields: Vec<&str> = issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["extra", "token_address", "beneficiary", "duration"]);
+    }
+
+    #[test]
+    fn test_cross_field_rule() {
+        let mut params = vesting_params();
+        params["cliff_duration"] = json!("31536001");
+        let issues = vesting_schema().validate(&params).unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "cliff_duration");
+    }
+
+    #[test]
+    fn test_array_element_path() {
+        let schema: TemplateSchema = serde_json::from_value(json!({
+            "parameters": [{ "name": "recipients", "type": "address[]", "required": true }]
+        })).unwrap();
+        let issues = schema.validate(&json!({ "recipients": ["0x1111111111111111111111111111111111111111", 7] }))
+            .unwrap_err();
+        assert_eq!(issues[0].field, "recipients[1]");
+    }
+
+    #[test]
+    fn test_zero_address_rejected() {
+        let mut params = vesting_params();
+        params["beneficiary"] = json!("0x0000000000000000000000000000000000000000");
+        let issues = vesting_schema().validate(&params).unwrap_err();
+        assert_eq!(issues, vec![ValidationIssue::new("beneficiary", "the zero address is not allowed here")]);
+
+        let schema: TemplateSchema = serde_json::from_value(json!({
+            "parameters": [{ "name": "recipients", "type": "address[]", "nonzero": true }]
+        })).unwrap();
+        let issues = schema.validate(&json!({
+            "recipients": ["0x1111111111111111111111111111111111111111", "0x0000000000000000000000000000000000000000"]
+        })).unwrap_err();
+        assert_eq!(issues[0].field, "recipients[1]");
+    }
+
+    #[test]
+    fn test_bad_checksum_rejected() {
+        let mut params = vesting_params();
+        params["token_address"] = json!("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        let issues = vesting_schema().validate(&params).unwrap_err();
+        assert_eq!(issues[0].field, "token_address");
+        assert!(issues[0].message.contains("EIP-55 checksum mismatch"), "{}", issues[0].message);
+    }
+
+    #[test]
+    fn test_signed_bounds() {
+        let schema: TemplateSchema = serde_json::from_value(json!({
+            "parameters": [{ "name": "offset", "type": "int32", "min": -10, "max": "10" }]
+        })).unwrap();
+        assert!(schema.validate(&json!({ "offset": -10 })).is_ok());
+        assert!(schema.validate(&json!({ "offset": -11 })).is_err());
+        assert!(schema.validate(&json!({ "offset": "11" })).is_err());
+        assert!(schema.validate(&json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_manifest_is_rejected() {
+        let schema: TemplateSchema = serde_json::from_value(json!({
+            "parameters": [{ "name": "owner", "type": "address" }],
+            "rules": [{ "left": "owner", "op": "<", "right": "owner" }]
+        })).unwrap();
+        assert!(schema.check().is_err());
+    }
+
+    #[test]
+    fn test_invalid_bounds_and_defaults_are_rejected() {
+        let check = |spec: Value| {
+            let schema: TemplateSchema = serde_json::from_value(json!({ "parameters": [spec] })).unwrap();
+            schema.check()
+        };
+        assert!(check(json!({ "name": "amount", "type": "uint256", "min": "1", "max": "0x10", "default": 5 })).is_ok());
+
+        let err = check(json!({ "name": "amount", "type": "uint256", "max": "1e18" })).unwrap_err();
+        assert_eq!(err, "parameter 'amount' has max \"1e18\", which is not an integer");
+        let err = check(json!({ "name": "owner", "type": "address", "min": 1 })).unwrap_err();
+        assert_eq!(err, "parameter 'owner' has a min but is not an integer");
+        let err = check(json!({ "name": "amount", "type": "uint256", "min": 10, "max": 1 })).unwrap_err();
+        assert_eq!(err, "parameter 'amount' has a min above its max");
+        let err = check(json!({ "name": "amount", "type": "uint256", "max": 10, "default": 11 })).unwrap_err();
+        assert_eq!(err, "default of parameter 'amount' is invalid: must be <= 10");
+        let err = check(json!({ "name": "owner", "type": "address", "default": "0x12" })).unwrap_err();
+        assert!(err.starts_with("default of parameter 'owner' is invalid"), "{}", err);
+    }
+}
@@ -0,0 +1,174 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::sol_template_engine::{SolTemplateEngine, TemplateError};
+
+/// How long the watcher waits for more changes before reloading, so that an editor
+/// saving several files (or writing one in several steps) triggers a single reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Outcome of the most recent template reload, as reported by `GET /api/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadStatus {
+    /// Incremented every time a new template set is swapped in; 1 after startup.
+    pub generation: u64,
+    pub template_count: usize,
+    pub loaded_at: i64,
+    /// Set when the latest reload failed; the previous template set is still being served.
+    pub last_error: Option<ReloadFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadFailure {
+    pub message: String,
+    pub failed_at: i64,
+}
+
+/// Holds the current `SolTemplateEngine` and swaps it for a freshly parsed one on reload.
+/// Handlers take a cheap `Arc` snapshot, so a reload never disturbs a request in flight.
+pub struct TemplateStore {
+    contracts_dir: PathBuf,
+    engine: RwLock<Arc<SolTemplateEngine>>,
+    status: RwLock<ReloadStatus>,
+}
+
+impl TemplateStore {
+    /// Parses every template in `contracts_dir`. Unlike a reload, a failure here is fatal.
+    pub fn new(contracts_dir: PathBuf) -> Result<Self, TemplateError> {
+        let engine = SolTemplateEngine::new(contracts_dir.clone())?;
+        let status = ReloadStatus {
+            generation: 1,
+            template_count: engine.list_templates().len(),
+            loaded_at: chrono::Utc::now().timestamp(),
+            last_error: None,
+        };
+        Ok(Self { contracts_dir, engine: RwLock::new(Arc::new(engine)), status: RwLock::new(status) })
+    }
+
+    /// The template set currently being served.
+    pub fn engine(&self) -> Arc<SolTemplateEngine> {
+        self.engine.read().unwrap().clone()
+    }
+
+    pub fn status(&self) -> ReloadStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Re-parses the contracts directory. On failure the last good template set is
+    /// kept and the error is recorded in [`Self::status`].
+    pub fn reload(&self) -> Result<(), TemplateError> {
+        match SolTemplateEngine::new(self.contracts_dir.clone()) {
+            Ok(engine) => {
+                let template_count = engine.list_templates().len();
+                *self.engine.write().unwrap() = Arc::new(engine);
+                let mut status = self.status.write().unwrap();
+                status.generation += 1;
+                status.template_count = template_count;
+                status.loaded_at = chrono::Utc::now().timestamp();
+                status.last_error = None;
+                println!("Reloaded {} templates from {:?}.", template_count, self.contracts_dir);
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Template reload failed, keeping the previous templates: {}", e);
+                self.status.write().unwrap().last_error = Some(ReloadFailure {
+                    message: e.to_string(),
+                    failed_at: chrono::Utc::now().timestamp(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Watches the contracts directory and reloads whenever a template or manifest changes.
+    /// Watching stops when the returned watcher is dropped.
+    pub fn watch(self: &Arc<Self>) -> notify::Result<RecommendedWatcher> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if event.paths.iter().any(|path| is_template_file(path)) => {
+                    let _ = tx.send(());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Template watcher error: {}", e),
+            }
+        })?;
+        watcher.watch(&self.contracts_dir, RecursiveMode::NonRecursive)?;
+
+        let store = Arc::clone(self);
+        thread::spawn(move || {
+            // Ends once the watcher, and with it the sender, is dropped.
+            while rx.recv().is_ok() {
+                while rx.recv_timeout(RELOAD_DEBOUNCE).is_ok() {}
+                let _ = store.reload();
+            }
+        });
+        Ok(watcher)
+    }
+}
+
+/// Files whose changes affect the template set: the templates and their manifests.
+fn is_template_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".sol.tera") || name.ends_with(".schema.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    fn store_with_template(dir: &Path) -> Arc<TemplateStore> {
+        fs::write(dir.join("A.sol.tera"), "contract A { uint x = {{ x }}; }").unwrap();
+        Arc::new(TemplateStore::new(dir.to_path_buf()).unwrap())
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_last_good_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_template(dir.path());
+
+        fs::write(dir.path().join("B.sol.tera"), "contract B { {% if %} }").unwrap();
+        assert!(store.reload().is_err());
+        let status = store.status();
+        assert_eq!(status.generation, 1);
+        assert!(status.last_error.unwrap().message.contains("B.sol.tera"));
+        let rendered = store.engine().render_template("A.sol.tera", &serde_json::json!({ "x": 1 })).unwrap();
+        assert_eq!(rendered, "contract A { uint x = 1; }");
+
+        fs::write(dir.path().join("B.sol.tera"), "contract B {}").unwrap();
+        store.reload().unwrap();
+        let status = store.status();
+        assert_eq!((status.generation, status.template_count), (2, 2));
+        assert!(status.last_error.is_none());
+    }
+
+    #[test]
+    fn test_watcher_reloads_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_template(dir.path());
+        let _watcher = store.watch().unwrap();
+
+        fs::write(dir.path().join("A.sol.tera"), "contract A { uint y = {{ x }}; }").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while store.status().generation == 1 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        let rendered = store.engine().render_template("A.sol.tera", &serde_json::json!({ "x": 2 })).unwrap();
+        assert_eq!(rendered, "contract A { uint y = 2; }");
+    }
+
+    #[test]
+    fn test_is_template_file() {
+        assert!(is_template_file(Path::new("/c/TokenVesting.sol.tera")));
+        assert!(is_template_file(Path::new("TokenVesting.schema.json")));
+        assert!(!is_template_file(Path::new("/c/.TokenVesting.sol.tera.swp")));
+    }
+}